@@ -26,6 +26,7 @@ pub enum Error {
     DirectivesHaveSingleName,
     EmptyLabel,
     InvalidImmediateValue,
+    InvalidMemoryArgument,
     NoClosingParen,
     NoOpcodeOnlyArguments,
     SecondLabel,
@@ -172,7 +173,8 @@ impl str::FromStr for Line {
                 },
                 Some((prev, Separator::Memory, after)) => {
                     let closing = after.find(')').ok_or_else(|| Error::NoClosingParen)?;
-                    let (memory_arg, after) = st.split_at(prev.len() + 1 + closing);
+                    // Including the closing parenthesis.
+                    let (memory_arg, after) = st.split_at(prev.len() + 1 + closing + 1);
 
                     ctx.append_part().push_str(memory_arg);
                     st = after;
@@ -322,7 +324,40 @@ impl str::FromStr for Memory {
     type Err = Error;
 
     fn from_str(st: &str) -> Result<Self, Error> {
-        unimplemented!("Memory argument parsing")
+        let st = st.trim();
+        let (segment, st) = match st.find(':') {
+            Some(idx) => (Some(register(&st[..idx])?), &st[idx+1..]),
+            None => (None, st),
+        };
+
+        let open = st.find('(').ok_or_else(|| Error::InvalidMemoryArgument)?;
+        let inner = st[open+1..].trim_end().strip_suffix(')').ok_or_else(|| Error::NoClosingParen)?;
+        let displacement = match st[..open].trim() {
+            "" => None,
+            value => Some(Value { value: value.parse().ok().ok_or_else(|| Error::InvalidImmediateValue)? }),
+        };
+
+        // The base is required, an index without it is not supported.
+        let mut parts = inner.split(',');
+        let base = register(parts.next().unwrap_or_default())?;
+        let index = parts.next().map(register).transpose()?;
+        let scale = parts.next()
+            .map(|scale| scale.trim().parse().ok().ok_or_else(|| Error::InvalidImmediateValue))
+            .transpose()?
+            .map(|value| Value { value });
+        if parts.next().is_some() {
+            return Err(Error::InvalidMemoryArgument);
+        }
+
+        Ok(Memory { segment, displacement, base, index, scale })
+    }
+}
+
+/// The name of a register operand within a memory argument.
+fn register(st: &str) -> Result<String, Error> {
+    match st.trim().strip_prefix('%') {
+        Some(name) if !name.is_empty() => Ok(name.to_string()),
+        _ => Err(Error::InvalidMemoryArgument),
     }
 }
 
@@ -364,4 +399,41 @@ mod tests {
         assert_eq!(directive.name, "word".to_string());
         assert_eq!(directive.arguments, vec!["1".to_string(), " 2".to_string()]);
     }
+
+    #[test]
+    fn parses_memory() {
+        let load: Line = "mov %rax, 8(%rsp)".parse().unwrap();
+        let statement = load.kind.as_statement().unwrap();
+        assert_eq!(statement.arguments, vec![
+            Argument::Register("rax".into()),
+            Argument::Memory(Memory {
+                segment: None,
+                displacement: Some(Value { value: 8 }),
+                base: "rsp".into(),
+                index: None,
+                scale: None,
+            }),
+        ]);
+
+        let store: Line = "mov -16(%rbp, %rcx, 8), %rdi".parse().unwrap();
+        let statement = store.kind.as_statement().unwrap();
+        assert_eq!(statement.arguments, vec![
+            Argument::Memory(Memory {
+                segment: None,
+                displacement: Some(Value { value: -16 }),
+                base: "rbp".into(),
+                index: Some("rcx".into()),
+                scale: Some(Value { value: 8 }),
+            }),
+            Argument::Register("rdi".into()),
+        ]);
+
+        let segment: Memory = "%fs:(%rax)".parse().unwrap();
+        assert_eq!(segment.segment, Some("fs".into()));
+        assert_eq!(segment.displacement, None);
+        assert_eq!(segment.base, "rax".to_string());
+
+        assert!(matches!("8(,%rcx,8)".parse::<Memory>(), Err(Error::InvalidMemoryArgument)));
+        assert!(matches!("8(%rsp".parse::<Memory>(), Err(Error::NoClosingParen)));
+    }
 }
@@ -0,0 +1,73 @@
+//! Executable memory for assembled code.
+use std::{io, mem, ptr, slice};
+
+/// A private mapping containing code, never writable and executable at the same time.
+///
+/// The code is copied into a fresh read-write mapping which is then remapped as read-execute.
+/// The mapping is released when the buffer is dropped.
+pub struct ExecutableBuffer {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl ExecutableBuffer {
+    /// Map the code as executable.
+    pub fn new(code: &[u8]) -> io::Result<Self> {
+        // Zero sized mappings are invalid.
+        let len = code.len().max(1);
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0)
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Unmaps on error from here.
+        let buffer = ExecutableBuffer { ptr, len };
+        unsafe {
+            ptr::copy_nonoverlapping(code.as_ptr(), ptr as *mut u8, code.len());
+        }
+
+        if unsafe { libc::mprotect(ptr, len, libc::PROT_READ | libc::PROT_EXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(buffer)
+    }
+
+    /// The mapped code.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    /// The address of the first instruction.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr as *const u8
+    }
+
+    /// Reinterpret the start of the code as a function pointer.
+    ///
+    /// # Safety
+    ///
+    /// `F` must be a function pointer type whose abi and signature match the assembled code, and
+    /// the pointer must not be called after the buffer has been dropped.
+    pub unsafe fn function<F: Copy>(&self) -> F {
+        assert_eq!(mem::size_of::<F>(), mem::size_of::<*const u8>(), "Not a function pointer");
+        mem::transmute_copy(&self.ptr)
+    }
+}
+
+impl Drop for ExecutableBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
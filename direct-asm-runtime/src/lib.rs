@@ -0,0 +1,53 @@
+//! The assembler of `direct-asm`, usable at runtime.
+//!
+//! This shares the att dialect and the dynasm lowering with the default backend of the proc macro,
+//! so code generated at runtime can be written in the same syntax as the compile-time stubs.
+//!
+//! ```no_run
+//! use direct_asm_runtime::{Assembler, ExecutableBuffer};
+//!
+//! let code = Assembler::new()
+//!     .assemble("mov %rax, %rdi\nadd %rax, %rsi\nret")
+//!     .unwrap();
+//! let buffer = ExecutableBuffer::new(&code).unwrap();
+//! let add: extern "sysv64" fn(u64, u64) -> u64 = unsafe { buffer.function() };
+//! assert_eq!(add(40, 2), 42);
+//! ```
+pub mod att;
+pub mod x86;
+
+#[cfg(unix)]
+mod exec;
+
+#[cfg(unix)]
+pub use exec::ExecutableBuffer;
+pub use x86::Error;
+
+/// Assembles the att dialect into x86-64 machine code.
+pub struct Assembler {
+    inner: x86::DynasmX86,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Assembler {
+            inner: x86::DynasmX86::new(),
+        }
+    }
+
+    /// Assemble a sequence of lines into the raw instruction bytes.
+    pub fn assemble(&mut self, input: &str) -> Result<Vec<u8>, Error> {
+        self.inner.assemble(input)
+    }
+
+    /// Assemble a sequence of lines, also returning the offset at which each line starts.
+    pub fn assemble_with_offsets(&mut self, input: &str) -> Result<(Vec<u8>, Vec<usize>), Error> {
+        self.inner.assemble_with_offsets(input)
+    }
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Assembler::new()
+    }
+}
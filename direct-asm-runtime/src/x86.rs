@@ -1,10 +1,10 @@
 //! Actual x86 assembler.
 //!
-//! Use 
-use crate::Assembler;
+//! Lowers the att dialect to dynasm statements and encodes them.
 use crate::att;
 
 use std::collections::HashMap;
+use std::fmt;
 use dynasm::{DynasmData, Ident, Number, NumericRepr, State, Stmt, Value};
 use dynasm::arch::{Arch, x64::{self, ast}};
 
@@ -17,8 +17,15 @@ pub struct DynasmX86 {
 #[derive(Debug)]
 pub enum Error {
     InvalidX64Register,
+    /// Memory operands are parsed but can not yet be encoded.
     UnsupportedArgument,
     UnsupportedDirective,
+    /// Dynasm emitted a statement other than plain bytes.
+    UnsupportedStatement,
+    /// A line could not be parsed.
+    Parse(att::Error),
+    /// Dynasm rejected the instruction, with its message.
+    Compile(String),
 }
 
 #[derive(Debug, Default)]
@@ -51,9 +58,12 @@ impl DynasmX86 {
         (state, &self.arch)
     }
 
-    fn generate_instruction_bytes(&self) -> Vec<u8> {
+    /// Encode all statements, also returning the offset at which each statement starts.
+    fn generate_instruction_bytes(&self) -> Result<(Vec<u8>, Vec<usize>), Error> {
         let mut instructions = Vec::new();
+        let mut offsets = Vec::with_capacity(self.statements.len() + 1);
         for stmt in &self.statements {
+            offsets.push(instructions.len());
             match stmt {
                 Stmt::Const(Value::Number(value)) => value.write_le_bytes(&mut instructions),
                 Stmt::Extend(slice) => instructions.extend_from_slice(slice),
@@ -62,10 +72,11 @@ impl DynasmX86 {
                 | Stmt::ExprExtend(_) 
                 | Stmt::DynamicLabel(_) 
                 | Stmt::Stmt(_) => unreachable!(),
-                _ => unimplemented!(),
+                _ => return Err(Error::UnsupportedStatement),
             }
         }
-        instructions
+        offsets.push(instructions.len());
+        Ok((instructions, offsets))
     }
 }
 
@@ -110,7 +121,7 @@ impl DynasmLine {
                 Ok(ast::CleanArg::Direct { reg })
             },
             att::Argument::Memory(_) => {
-                // FIXME.
+                // FIXME: lower to an indirect dynasm argument.
                 Err(Error::UnsupportedArgument)
             },
             att::Argument::Immediate(att::Value { value }) => {
@@ -122,24 +133,57 @@ impl DynasmLine {
     }
 }
 
-impl Assembler for DynasmX86 {
-    fn assemble(&mut self, input: &str) -> Vec<u8> {
+impl DynasmX86 {
+    /// Assemble all lines of the input.
+    ///
+    /// Statements of previous calls are discarded, the encoded bytes only contain this input.
+    pub fn assemble(&mut self, input: &str) -> Result<Vec<u8>, Error> {
+        self.assemble_with_offsets(input).map(|(code, _)| code)
+    }
+
+    /// Assemble all lines, also returning the offset at which each line starts.
+    pub fn assemble_with_offsets(&mut self, input: &str) -> Result<(Vec<u8>, Vec<usize>), Error> {
         use x64::AssembleX64;
 
+        self.statements.clear();
+        let mut line_starts = vec![];
         let (mut state, arch) = self.state();
 
         for line in input.lines() {
-            let line = DynasmLine::convert(line.parse().unwrap()).unwrap();
+            line_starts.push(state.stmts.len());
+            let line = line.parse().map_err(Error::Parse)?;
+            let line = DynasmLine::convert(line)?;
 
             if let Some(features) = line.set_features {
                 state.file_data.current_arch.set_features(&features);
             }
 
             if let Some(instruction) = line.instruction {
-                state.compile_instruction(arch, instruction).unwrap();
+                state.compile_instruction(arch, instruction)
+                    .map_err(|err| Error::Compile(format!("{:?}", err)))?;
             }
         }
 
-        self.generate_instruction_bytes()
+        let (code, stmt_offsets) = self.generate_instruction_bytes()?;
+        let offsets = line_starts
+            .into_iter()
+            .map(|stmt| stmt_offsets[stmt])
+            .collect();
+        Ok((code, offsets))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidX64Register => f.write_str("invalid x86-64 register"),
+            Error::UnsupportedArgument => f.write_str("unsupported argument"),
+            Error::UnsupportedDirective => f.write_str("unsupported directive"),
+            Error::UnsupportedStatement => f.write_str("unsupported statement"),
+            Error::Parse(err) => write!(f, "invalid line: {:?}", err),
+            Error::Compile(msg) => write!(f, "invalid instruction: {}", msg),
+        }
     }
 }
+
+impl std::error::Error for Error {}
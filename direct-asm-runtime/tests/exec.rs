@@ -0,0 +1,42 @@
+use direct_asm_runtime::{Assembler, Error, ExecutableBuffer};
+
+#[test]
+fn call_assembled() {
+    let code = Assembler::new()
+        .assemble("mov %rax, %rdi\nadd %rax, %rsi\nret")
+        .unwrap();
+    let buffer = ExecutableBuffer::new(&code).unwrap();
+    let add: extern "sysv64" fn(u64, u64) -> u64 = unsafe { buffer.function() };
+    assert_eq!(add(40, 2), 42);
+}
+
+#[test]
+fn reuse_assembler() {
+    let mut assembler = Assembler::new();
+    let first = assembler.assemble("syscall").unwrap();
+    let second = assembler.assemble("ret").unwrap();
+    assert_eq!(first, [0x0f, 0x05]);
+    assert_eq!(second, [0xc3]);
+}
+
+#[test]
+fn call_raw() {
+    let buffer = ExecutableBuffer::new(&[0x48, 0x89, 0xf8, 0xc3]).unwrap();
+    let identity: extern "sysv64" fn(u64) -> u64 = unsafe { buffer.function() };
+    assert_eq!(identity(42), 42);
+}
+
+#[test]
+fn line_offsets() {
+    let (code, offsets) = Assembler::new()
+        .assemble_with_offsets("push %rbx\nmov %rax, %rdi\n\npop %rbx\nret")
+        .unwrap();
+    assert_eq!(code.len(), 6);
+    assert_eq!(offsets, [0, 1, 4, 4, 5]);
+}
+
+#[test]
+fn memory_operand_unsupported() {
+    let result = Assembler::new().assemble("mov %rax, 8(%rsp)\nret");
+    assert!(matches!(result, Err(Error::UnsupportedArgument)), "{:?}", result);
+}
@@ -0,0 +1,394 @@
+//! Calling conventions and the placement of arguments.
+//!
+//! The assembled body is opaque to the compiler, it only sees the declared signature. Hence the
+//! body must agree with the convention on where each argument arrives and where the result is
+//! expected. We classify the signature syntactically and reject anything that would not be passed
+//! in a general purpose register or a single stack slot.
+use quote::{quote, ToTokens};
+
+/// The calling convention that the assembled body was written against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Abi {
+    /// `extern "C"`, the default convention of the target.
+    C,
+    /// `extern "system"`, same as `C` on all x86-64 targets.
+    System,
+    /// `extern "sysv64"`, the System V convention even on Windows targets.
+    SysV64,
+    /// `extern "win64"`, the Microsoft convention even on Unix targets.
+    Win64,
+}
+
+/// The instruction set that the body is assembled for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arch {
+    X86_64,
+    /// 32-bit x86, for which only the stack based `C` convention is supported.
+    X86,
+    /// 64-bit ARM, with the procedure call standard as the `C` convention.
+    Aarch64,
+}
+
+/// The register assignment rules, after resolving the target dependent abis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Convention {
+    SysV,
+    Microsoft,
+    /// The 32-bit `cdecl`, all arguments on the stack.
+    Cdecl,
+    /// The procedure call standard of 64-bit ARM.
+    Aapcs64,
+}
+
+/// Where a single argument or the return value is passed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    /// In a general purpose register.
+    Register(&'static str),
+    /// In the stack slot at the given offset from the stack pointer on entry to the function.
+    Stack(usize),
+}
+
+/// A type whose size we can not know syntactically but which must fit into a register.
+pub struct Unclassified {
+    pub ty: syn::Type,
+    pub name: String,
+}
+
+/// The argument placement of a whole signature.
+pub struct Layout {
+    pub convention: Convention,
+    pub arguments: Vec<(String, Location)>,
+    pub ret: Option<Location>,
+    /// Types that are only checked by the compiler, after expansion.
+    pub unclassified: Vec<Unclassified>,
+}
+
+enum Class {
+    /// Passed in one general purpose register.
+    Integer,
+    /// Probably passed in a register but the size must be checked by the compiler.
+    Unknown,
+    /// Not passed or returned at all.
+    Nothing,
+}
+
+impl Abi {
+    pub fn from_declaration(abi: &syn::Abi) -> Self {
+        let name = match &abi.name {
+            // A bare `extern` is the same as `extern "C"`.
+            None => return Abi::C,
+            Some(name) => name.value(),
+        };
+
+        match name.as_str() {
+            "C" => Abi::C,
+            "system" => Abi::System,
+            "sysv64" => Abi::SysV64,
+            "win64" => Abi::Win64,
+            other => panic!("Unsupported abi `{}` (C, system, sysv64, win64)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Abi::C => "C",
+            Abi::System => "system",
+            Abi::SysV64 => "sysv64",
+            Abi::Win64 => "win64",
+        }
+    }
+
+    /// The convention used to place arguments.
+    ///
+    /// FIXME: `C` and `system` are the Microsoft convention on Windows but a proc macro can not
+    /// inspect the target. Declare `sysv64` or `win64` explicitly when this matters.
+    pub fn convention(self, arch: Arch) -> Convention {
+        match (arch, self) {
+            (Arch::X86_64, Abi::C | Abi::System | Abi::SysV64) => Convention::SysV,
+            (Arch::X86_64, Abi::Win64) => Convention::Microsoft,
+            (Arch::X86, Abi::C | Abi::System) => Convention::Cdecl,
+            (Arch::Aarch64, Abi::C | Abi::System) => Convention::Aapcs64,
+            (_, _) => panic!("`extern \"{}\"` only exists on x86-64, declare `extern \"C\"`", self.name()),
+        }
+    }
+
+    /// Fail compilation for targets that can not execute the assembled code.
+    pub fn target_check(self, arch: Arch) -> proc_macro2::TokenStream {
+        let (name, target) = match arch {
+            Arch::X86_64 => ("x86-64", "x86_64"),
+            Arch::X86 => ("x86", "x86"),
+            Arch::Aarch64 => ("aarch64", "aarch64"),
+        };
+        let message = format!(
+            "Function declared `extern \"{}\"` was assembled for {} but the target is not {}",
+            self.name(), name, name);
+        quote! {
+            #[cfg(not(target_arch = #target))]
+            compile_error!(#message);
+        }
+    }
+
+    /// Fail compilation where the registers of named arguments were chosen incorrectly.
+    ///
+    /// See `convention`, we assume the System V convention for `C` and `system`.
+    pub fn convention_check(self) -> proc_macro2::TokenStream {
+        match self {
+            Abi::C | Abi::System => {
+                let message = format!(
+                    "Arguments named assuming `extern \"{}\"` is `sysv64`, declare the abi explicitly",
+                    self.name());
+                quote! {
+                    #[cfg(windows)]
+                    compile_error!(#message);
+                }
+            },
+            Abi::SysV64 | Abi::Win64 => proc_macro2::TokenStream::new(),
+        }
+    }
+}
+
+impl ToTokens for Abi {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let name = self.name();
+        tokens.extend(quote!(extern #name));
+    }
+}
+
+impl Convention {
+    fn integer_registers(self) -> &'static [&'static str] {
+        match self {
+            Convention::SysV => &["rdi", "rsi", "rdx", "rcx", "r8", "r9"],
+            Convention::Microsoft => &["rcx", "rdx", "r8", "r9"],
+            Convention::Cdecl => &[],
+            Convention::Aapcs64 => &["x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7"],
+        }
+    }
+
+    /// Offset of the first stack argument from the stack pointer on entry.
+    fn first_stack_slot(self) -> usize {
+        match self {
+            // Only the return address.
+            Convention::SysV => 8,
+            // The return address and the 32 byte shadow space for the register arguments.
+            Convention::Microsoft => 40,
+            Convention::Cdecl => 4,
+            // The return address is in the link register.
+            Convention::Aapcs64 => 0,
+        }
+    }
+
+    /// The size of a register and of a stack slot.
+    pub fn word_size(self) -> usize {
+        match self {
+            Convention::SysV | Convention::Microsoft | Convention::Aapcs64 => 8,
+            Convention::Cdecl => 4,
+        }
+    }
+
+    pub fn stack_pointer(self) -> &'static str {
+        match self {
+            Convention::SysV | Convention::Microsoft => "rsp",
+            Convention::Cdecl => "esp",
+            Convention::Aapcs64 => "sp",
+        }
+    }
+
+    pub fn return_register(self) -> &'static str {
+        match self {
+            Convention::SysV | Convention::Microsoft => "rax",
+            Convention::Cdecl => "eax",
+            Convention::Aapcs64 => "x0",
+        }
+    }
+}
+
+impl Layout {
+    /// Classify each argument of a signature.
+    ///
+    /// Panics for types that are definitely not passed in a general purpose register, such as
+    /// floating point numbers, 128-bit integers and aggregates by value.
+    pub fn classify(sig: &syn::Signature, abi: Abi, arch: Arch) -> Self {
+        let convention = abi.convention(arch);
+        let registers = convention.integer_registers();
+        let mut layout = Layout {
+            convention,
+            arguments: vec![],
+            ret: None,
+            unclassified: vec![],
+        };
+
+        let mut next_stack = convention.first_stack_slot();
+        for (idx, input) in sig.inputs.iter().enumerate() {
+            let name = match input {
+                syn::FnArg::Typed(typed) => {
+                    let name = argument_name(&typed.pat, idx);
+                    match classify(&typed.ty, &name, convention) {
+                        Class::Integer => {},
+                        Class::Unknown => layout.unclassified.push(Unclassified {
+                            ty: (*typed.ty).clone(),
+                            name: name.clone(),
+                        }),
+                        Class::Nothing => panic!("Argument `{}` has no value to pass", name),
+                    }
+                    name
+                },
+                // The forwarding method passes a borrowed receiver as a pointer.
+                syn::FnArg::Receiver(receiver) if receiver.reference.is_some() => "self".to_string(),
+                syn::FnArg::Receiver(_) => panic!("A receiver must be borrowed, `self` by value is not supported"),
+            };
+
+            // Both conventions assign registers by position, all our arguments are integers.
+            let location = match registers.get(idx) {
+                Some(register) => Location::Register(register),
+                None => {
+                    let slot = next_stack;
+                    next_stack += convention.word_size();
+                    Location::Stack(slot)
+                },
+            };
+
+            layout.arguments.push((name, location));
+        }
+
+        if let syn::ReturnType::Type(_, ty) = &sig.output {
+            let ret = Location::Register(convention.return_register());
+            match classify(ty, "return value", convention) {
+                Class::Integer => layout.ret = Some(ret),
+                Class::Unknown => {
+                    layout.unclassified.push(Unclassified {
+                        ty: (**ty).clone(),
+                        name: "return value".into(),
+                    });
+                    layout.ret = Some(ret);
+                },
+                Class::Nothing => {},
+            }
+        }
+
+        layout
+    }
+
+    /// Panic if any argument is passed on the stack.
+    pub fn deny_stack(&self) {
+        for (name, location) in &self.arguments {
+            if let Location::Stack(_) = location {
+                panic!("Argument `{}` is passed on the stack at {}, not in a register", name, self.describe(location));
+            }
+        }
+    }
+
+    /// Compile time assertions for types that we could not classify syntactically.
+    pub fn size_checks(&self) -> proc_macro2::TokenStream {
+        let mut checks = proc_macro2::TokenStream::new();
+        let size = self.convention.word_size();
+        for Unclassified { ty, name } in &self.unclassified {
+            let message = format!("`{}` must be at most {} bytes to be passed in a register", name, size);
+            checks.extend(quote! {
+                const _: () = assert!(::core::mem::size_of::<#ty>() <= #size, #message);
+            });
+        }
+        checks
+    }
+
+    /// A human readable listing of the argument locations, as documentation lines.
+    pub fn listing(&self) -> Vec<String> {
+        let convention = match self.convention {
+            Convention::SysV => "System V",
+            Convention::Microsoft => "Microsoft x64",
+            Convention::Cdecl => "cdecl",
+            Convention::Aapcs64 => "AArch64 procedure call standard",
+        };
+
+        let mut lines = vec![
+            " # Registers".to_string(),
+            String::new(),
+            format!(" Arguments as placed by the {} calling convention:", convention),
+            String::new(),
+        ];
+
+        for (name, location) in &self.arguments {
+            lines.push(format!(" - `{}`: `{}`", name, self.describe(location)));
+        }
+
+        if let Some(location) = &self.ret {
+            lines.push(format!(" - return value: `{}`", self.describe(location)));
+        }
+
+        lines
+    }
+
+    /// The register or the stack slot, such as `[rsp+8]`.
+    fn describe(&self, location: &Location) -> String {
+        match location {
+            Location::Register(register) => register.to_string(),
+            Location::Stack(offset) if self.convention == Convention::Aapcs64 => format!("[sp, #{}]", offset),
+            Location::Stack(offset) => format!("[{}+{}]", self.convention.stack_pointer(), offset),
+        }
+    }
+}
+
+fn argument_name(pat: &syn::Pat, idx: usize) -> String {
+    match pat {
+        syn::Pat::Ident(ident) => ident.ident.to_string(),
+        _ => format!("argument {}", idx),
+    }
+}
+
+fn classify(ty: &syn::Type, name: &str, convention: Convention) -> Class {
+    match ty {
+        syn::Type::Ptr(ptr) => pointer(&ptr.elem, name),
+        syn::Type::Reference(reference) => pointer(&reference.elem, name),
+        syn::Type::BareFn(_) => Class::Integer,
+        syn::Type::Never(_) => Class::Nothing,
+        syn::Type::Tuple(tuple) if tuple.elems.is_empty() => Class::Nothing,
+        syn::Type::Paren(paren) => classify(&paren.elem, name, convention),
+        syn::Type::Group(group) => classify(&group.elem, name, convention),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let primitive = match path.path.get_ident() {
+                Some(ident) => ident.to_string(),
+                None => return Class::Unknown,
+            };
+
+            match primitive.as_str() {
+                "i64" | "u64" if convention == Convention::Cdecl => {
+                    panic!("`{}` is a 64-bit integer, passed in two slots on x86", name)
+                },
+                | "i8" | "i16" | "i32" | "i64" | "isize"
+                | "u8" | "u16" | "u32" | "u64" | "usize"
+                | "bool" | "char" => Class::Integer,
+                "f32" | "f64" => panic!("`{}` is a float, passed in a vector register", name),
+                "i128" | "u128" => panic!("`{}` is a 128-bit integer, passed in two registers", name),
+                _ => Class::Unknown,
+            }
+        },
+        _ => panic!("`{}` can not be passed in a general purpose register", name),
+    }
+}
+
+/// Classify a pointer or reference by its pointee.
+///
+/// Pointers to slices, `str` and trait objects carry a length or a vtable, they are passed in two
+/// registers and would shift all later arguments:
+///
+/// ```compile_fail
+/// #[direct_asm::assemble(backend = "gnu-as")]
+/// unsafe extern "sysv64" fn after_slice(s: &[u8], n: u64) -> u64 {
+///     "mov rax, {n}";
+///     "ret";
+/// }
+/// ```
+fn pointer(pointee: &syn::Type, name: &str) -> Class {
+    let metadata = match pointee {
+        syn::Type::Paren(paren) => return pointer(&paren.elem, name),
+        syn::Type::Group(group) => return pointer(&group.elem, name),
+        syn::Type::Slice(_) | syn::Type::TraitObject(_) => true,
+        syn::Type::Path(path) => path.qself.is_none() && path.path.is_ident("str"),
+        _ => false,
+    };
+
+    if metadata {
+        panic!("`{}` is a pointer with metadata, passed in two registers", name);
+    }
+    Class::Integer
+}
@@ -0,0 +1,123 @@
+//! Forwarding functions for associated functions, methods and safe functions.
+//!
+//! A foreign declaration is an item of a module, it can not appear inside an `impl` or `trait`.
+//! Instead the associated function forwards its arguments to a declaration that is nested within
+//! its body, together with the symbol definition. Nested items can not name `Self` or the generic
+//! parameters of the surrounding implementation, hence neither can the signature. A borrowed
+//! receiver is passed as an untyped pointer in the first argument, named `{self}` in the body.
+//!
+//! The same forwarding gives functions declared `safe` a safe signature. That the code is sound
+//! for all arguments is the attestation of the author, it is documented on the function.
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub struct Forward<'a> {
+    pub attrs: &'a [syn::Attribute],
+    pub visibility: &'a syn::Visibility,
+    /// The signature of the associated function, without an abi.
+    pub sig: &'a syn::Signature,
+    /// Declare the function safe to call.
+    pub safe: bool,
+}
+
+impl Forward<'_> {
+    /// Panic for signatures that the nested declaration can not repeat.
+    pub fn check(sig: &syn::Signature) {
+        if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+            panic!("Assembled functions can not be generic");
+        }
+
+        let types = sig.inputs
+            .iter()
+            .filter_map(|input| match input {
+                syn::FnArg::Typed(typed) => Some(quote!(#typed)),
+                syn::FnArg::Receiver(_) => None,
+            })
+            .chain(match &sig.output {
+                syn::ReturnType::Type(_, ty) => Some(quote!(#ty)),
+                syn::ReturnType::Default => None,
+            });
+        for ty in types {
+            if names_self(ty) {
+                panic!("`Self` can not be named in the signature of an assembled function, name the type instead");
+            }
+        }
+    }
+
+    /// The signature of the foreign declaration, where the receiver is a pointer.
+    pub fn declaration(sig: &syn::Signature) -> syn::Signature {
+        let mut sig = sig.clone();
+        for input in sig.inputs.iter_mut() {
+            if let syn::FnArg::Receiver(receiver) = input {
+                *input = match receiver.mutability {
+                    Some(_) => syn::parse_quote!(this: *mut ::core::ffi::c_void),
+                    None => syn::parse_quote!(this: *const ::core::ffi::c_void),
+                };
+            }
+        }
+        sig
+    }
+
+    /// The associated function containing `items`, calling the declaration of the same name.
+    pub fn function(&self, items: TokenStream) -> TokenStream {
+        let ident = &self.sig.ident;
+        self.wrap(|arguments| quote! {
+            #items
+
+            unsafe { #ident(#(#arguments),*) }
+        })
+    }
+
+    /// A function with the signature, whose body is built from expressions of its arguments.
+    pub fn wrap(&self, body: impl FnOnce(&[TokenStream]) -> TokenStream) -> TokenStream {
+        let mut sig = self.sig.clone();
+        sig.unsafety = if self.safe { None } else { Some(Default::default()) };
+
+        let mut arguments = vec![];
+        for (idx, input) in sig.inputs.iter_mut().enumerate() {
+            match input {
+                syn::FnArg::Receiver(receiver) => arguments.push(match receiver.mutability {
+                    Some(_) => quote!(self as *mut Self as *mut ::core::ffi::c_void),
+                    None => quote!(self as *const Self as *const ::core::ffi::c_void),
+                }),
+                syn::FnArg::Typed(typed) => {
+                    // Patterns are matched by the declaration, forward the plain value.
+                    let ident = quote::format_ident!("argument{}", idx);
+                    *typed.pat = syn::parse_quote!(#ident);
+                    arguments.push(quote!(#ident));
+                },
+            }
+        }
+
+        let attrs = self.attrs;
+        let attestation = if self.safe { Self::attestation() } else { vec![] };
+        let vis = self.visibility;
+        let body = body(&arguments);
+        quote! {
+            #(#attrs)*
+            #(#[doc = #attestation])*
+            #[inline(always)]
+            #vis #sig {
+                #body
+            }
+        }
+    }
+
+    fn attestation() -> Vec<&'static str> {
+        vec![
+            "",
+            " # Soundness",
+            "",
+            " This function calls assembled code. It was declared safe by its author, with",
+            " `#[assemble(safe)]`, attesting that the code is sound for all arguments.",
+        ]
+    }
+}
+
+fn names_self(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => ident == "Self",
+        proc_macro2::TokenTree::Group(group) => names_self(group.stream()),
+        _ => false,
+    })
+}
@@ -0,0 +1,119 @@
+//! Generated prologue and epilogue for functions with a stack frame.
+//!
+//! The frame is set up as by common compilers: the frame pointer is pushed and set to the stack
+//! pointer, followed by the saved registers and the local space. The local space is chosen such
+//! that the stack pointer is 16-byte aligned inside the body, as required for calls.
+//!
+//! ```text
+//! [rbp+16]        stack arguments
+//! [rbp+8]         return address
+//! [rbp]           previous rbp
+//! [rbp-8*n]       saved registers
+//! [rsp]           local space, at least `frame` bytes
+//! ```
+//!
+//! Each `ret` in the body is replaced with the epilogue. The body must leave the stack pointer
+//! where it found it, the epilogue releases the local space by adding to it.
+use crate::unwind::{dwarf_register, RBP, RSP};
+use crate::Backend;
+
+pub struct Frame {
+    /// Requested size of the local space.
+    pub size: u64,
+    /// Registers to save and restore, in order of pushing.
+    pub save: Vec<String>,
+}
+
+impl Frame {
+    /// Validate the saved registers.
+    pub fn new(size: u64, save: Vec<String>) -> Self {
+        for register in &save {
+            match dwarf_register(register) {
+                Some(_) if register == "rsp" || register == "rbp" => {
+                    panic!("`{}` can not be saved, it is managed by the frame", register)
+                },
+                Some(_) => {},
+                None => panic!("`{}` is not a 64-bit general purpose register", register),
+            }
+        }
+
+        Frame { size, save }
+    }
+
+    /// The local space, rounded such that the stack stays aligned.
+    fn local_space(&self) -> u64 {
+        let saved = 8 * self.save.len() as u64;
+        // After pushing `rbp` the stack pointer is aligned to 16 bytes.
+        (saved + self.size).div_ceil(16) * 16 - saved
+    }
+
+    /// Surround the body with the prologue and replace each `ret` with the epilogue.
+    ///
+    /// The result contains call frame directives describing the frame.
+    pub fn wrap(&self, body: &str, backend: &Backend) -> String {
+        let reg = |name| backend.register(name);
+        let mut lines = vec![
+            format!("push {}", reg("rbp")),
+            ".cfi_adjust_cfa_offset 8".to_string(),
+            format!(".cfi_offset {}, -16", RBP),
+            format!("mov {}, {}", reg("rbp"), reg("rsp")),
+            format!(".cfi_def_cfa_register {}", RBP),
+        ];
+
+        for (idx, register) in self.save.iter().enumerate() {
+            lines.push(format!("push {}", backend.register(register)));
+            let number = dwarf_register(register).unwrap();
+            lines.push(format!(".cfi_offset {}, -{}", number, 24 + 8 * idx));
+        }
+
+        let local_space = self.local_space();
+        if local_space > 0 {
+            lines.push(format!("sub {}, {}", reg("rsp"), local_space));
+        }
+
+        let mut returns = false;
+        for line in body.lines().filter(|line| !line.trim().is_empty()) {
+            returns = line.trim() == "ret";
+            if returns {
+                lines.extend(self.epilogue(backend));
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+
+        // Do not run off the end of the code.
+        if !returns {
+            lines.extend(self.epilogue(backend));
+        }
+
+        lines.join("\n")
+    }
+
+    fn epilogue(&self, backend: &Backend) -> Vec<String> {
+        let reg = |name| backend.register(name);
+        let mut lines = vec![
+            // Code after this return is still inside the frame.
+            ".cfi_remember_state".to_string(),
+        ];
+
+        let local_space = self.local_space();
+        if local_space > 0 {
+            lines.push(format!("add {}, {}", reg("rsp"), local_space));
+        }
+
+        for register in self.save.iter().rev() {
+            lines.push(format!("pop {}", backend.register(register)));
+            lines.push(format!(".cfi_restore {}", dwarf_register(register).unwrap()));
+        }
+
+        lines.extend(vec![
+            format!("pop {}", reg("rbp")),
+            format!(".cfi_restore {}", RBP),
+            format!(".cfi_def_cfa {}, 8", RSP),
+            "ret".to_string(),
+            ".cfi_restore_state".to_string(),
+        ]);
+
+        lines
+    }
+}
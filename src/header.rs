@@ -0,0 +1,210 @@
+//! C declarations of exported functions.
+//!
+//! A header collects the declarations of all functions exported to it, one per line. Each
+//! expansion replaces the line of its own symbol and keeps the others, so that several functions
+//! and files can share one header. Delete the header to drop declarations of removed functions.
+//!
+//! The declared symbol is a `#[no_mangle]` static, such that it is also exported from a `cdylib`.
+//! A function symbol of `global_asm!` would not be.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::abi::Abi;
+
+pub struct Header {
+    /// The header file, relative to the manifest of the crate.
+    pub path: String,
+}
+
+impl Header {
+    /// Add or replace the declaration of `link_name` with the signature `sig`.
+    pub fn declare(&self, link_name: &str, sig: &syn::Signature, abi: Abi) {
+        let declaration = declaration(link_name, sig, abi);
+        let path = self.full_path();
+
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        let mut declarations = existing
+            .lines()
+            .filter(|line| line.starts_with("extern "))
+            .filter_map(|line| Some((declared_name(line)?.to_string(), line.to_string())))
+            .collect::<BTreeMap<_, _>>();
+        declarations.insert(link_name.to_string(), declaration);
+
+        let content = self.content(declarations.values());
+        // Do not touch the file when nothing changed, it may be a dependency of a C build.
+        if content == existing {
+            return;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .unwrap_or_else(|err| panic!("Failed to create directory of header: {}", err));
+        }
+        fs::write(&path, content)
+            .unwrap_or_else(|err| panic!("Failed to write header `{}`: {}", path.display(), err));
+    }
+
+    fn full_path(&self) -> PathBuf {
+        let manifest = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+        Path::new(&manifest).join(&self.path)
+    }
+
+    fn content<'a>(&self, declarations: impl Iterator<Item=&'a String>) -> String {
+        let guard = Path::new(&self.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch.to_ascii_uppercase() } else { '_' })
+            .collect::<String>();
+        let guard = format!("DIRECT_ASM_{}", guard);
+
+        let mut lines = vec![
+            "/* Generated by direct-asm, do not edit. */".to_string(),
+            format!("#ifndef {}", guard),
+            format!("#define {}", guard),
+            String::new(),
+            "#include <stdbool.h>".to_string(),
+            "#include <stddef.h>".to_string(),
+            "#include <stdint.h>".to_string(),
+            String::new(),
+        ];
+        lines.extend(declarations.cloned());
+        lines.push(String::new());
+        lines.push("#endif".to_string());
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// The C declaration of a function, on a single line.
+fn declaration(link_name: &str, sig: &syn::Signature, abi: Abi) -> String {
+    let mut arguments = vec![];
+    for input in &sig.inputs {
+        let typed = match input {
+            syn::FnArg::Typed(typed) => typed,
+            syn::FnArg::Receiver(_) => panic!("Methods with a receiver can not be exported to C"),
+        };
+
+        let ty = c_type(&typed.ty);
+        match &*typed.pat {
+            syn::Pat::Ident(ident) if !ident.ident.to_string().starts_with('_') => {
+                arguments.push(format!("{}{}", pointer_space(&ty), ident.ident));
+            },
+            _ => arguments.push(ty),
+        }
+    }
+
+    if arguments.is_empty() {
+        arguments.push("void".to_string());
+    }
+
+    let (noreturn, ret) = match &sig.output {
+        syn::ReturnType::Default => ("", "void".to_string()),
+        syn::ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Never(_) => ("_Noreturn ", "void".to_string()),
+            ty => ("", c_type(ty)),
+        },
+    };
+
+    // Only the explicit conventions differ from the default of the C compiler.
+    let convention = match abi {
+        Abi::C | Abi::System => "",
+        Abi::SysV64 => "__attribute__((sysv_abi)) ",
+        Abi::Win64 => "__attribute__((ms_abi)) ",
+    };
+
+    format!("extern {}{}{}{}({});", noreturn, convention, pointer_space(&ret), link_name, arguments.join(", "))
+}
+
+/// The type followed by a space, unless it ends in a pointer.
+fn pointer_space(ty: &str) -> String {
+    if ty.ends_with('*') {
+        ty.to_string()
+    } else {
+        format!("{} ", ty)
+    }
+}
+
+/// The name of a declaration line written by `declaration`.
+fn declared_name(line: &str) -> Option<&str> {
+    // The arguments are the last parenthesis, after any attributes.
+    let head = &line[..line.rfind('(')?];
+    head.rsplit([' ', '*']).next()
+}
+
+/// The C type equivalent to a Rust type in a signature.
+fn c_type(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Ptr(ptr) => c_pointer(&ptr.elem, ptr.mutability.is_none()),
+        syn::Type::Reference(reference) => c_pointer(&reference.elem, reference.mutability.is_none()),
+        // A function pointer type would need a typedef, any code pointer converts from `void *`.
+        syn::Type::BareFn(_) => "void *".to_string(),
+        syn::Type::Paren(paren) => c_type(&paren.elem),
+        syn::Type::Group(group) => c_type(&group.elem),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let name = path.path.segments.last().map(|segment| segment.ident.to_string());
+            match name.as_deref().and_then(primitive) {
+                Some(ty) => ty.to_string(),
+                None => panic!("`{}` has no C equivalent, use a primitive type", quote::quote!(#ty)),
+            }
+        },
+        _ => panic!("`{}` has no C equivalent, use a primitive type", quote::quote!(#ty)),
+    }
+}
+
+/// A pointer to `pointee`, which is `const` unless the pointer is mutable.
+///
+/// The qualifier of a pointer to a pointer follows the inner pointer, as in `const char *const *`.
+fn c_pointer(pointee: &syn::Type, constant: bool) -> String {
+    let pointee = c_type_pointee(pointee);
+    let constness = if constant { "const " } else { "" };
+    if pointee.ends_with('*') {
+        format!("{}{}*", pointee, constness)
+    } else {
+        format!("{}{} *", constness, pointee)
+    }
+}
+
+/// The type behind a pointer, where unknown types are opaque.
+fn c_type_pointee(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let name = path.path.segments.last().map(|segment| segment.ident.to_string());
+            name.as_deref().and_then(primitive).unwrap_or("void").to_string()
+        },
+        syn::Type::Ptr(_) | syn::Type::Reference(_) => c_type(ty),
+        _ => "void".to_string(),
+    }
+}
+
+fn primitive(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "i8" => "int8_t",
+        "i16" => "int16_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "isize" => "intptr_t",
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        "bool" => "bool",
+        "char" => "uint32_t",
+        "c_void" => "void",
+        "c_char" => "char",
+        "c_schar" => "signed char",
+        "c_uchar" => "unsigned char",
+        "c_short" => "short",
+        "c_ushort" => "unsigned short",
+        "c_int" => "int",
+        "c_uint" => "unsigned int",
+        "c_long" => "long",
+        "c_ulong" => "unsigned long",
+        "c_longlong" => "long long",
+        "c_ulonglong" => "unsigned long long",
+        _ => return None,
+    })
+}
@@ -0,0 +1,133 @@
+//! Lowering to inline assembly, for code that is too short to be worth a call.
+//!
+//! The pre-assembled bytes become the template of an `asm!` in the body of the function, with each
+//! argument as an input in the register the calling convention would have used. The final `ret`
+//! is removed so that execution continues after the `asm!`, with the return value in `rax`. All
+//! registers that the convention lets a callee clobber are marked as clobbered.
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::abi::{Convention, Layout, Location};
+
+pub struct Inline<'a> {
+    pub code: &'a [u8],
+    pub layout: &'a Layout,
+    pub sig: &'a syn::Signature,
+}
+
+impl Inline<'_> {
+    /// Remove the final `ret` of the body.
+    ///
+    /// The code continues after the inline assembly instead, which it can not do from any other
+    /// return. A body without `ret` must not return at all.
+    pub fn strip_return(body: &str) -> String {
+        let mut lines = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+
+        if lines.last().map(|line| line.trim()) == Some("ret") {
+            lines.pop();
+        }
+
+        if lines.iter().any(|line| line.trim() == "ret") {
+            panic!("Inline code can only return at its end, with a single `ret`");
+        }
+
+        lines.join("\n")
+    }
+
+    /// The body of the function, given expressions for its arguments.
+    pub fn body(&self, arguments: &[TokenStream]) -> TokenStream {
+        let template = match self.code {
+            [] => String::new(),
+            code => crate::symbol::byte_directive(code),
+        };
+
+        let mut operands = vec![];
+        let parameters = self.sig.inputs.iter().zip(&self.layout.arguments).zip(arguments);
+        for ((input, (name, location)), argument) in parameters {
+            let register = match location {
+                Location::Register(register) => *register,
+                Location::Stack(_) => panic!("Argument `{}` would be on the stack, which inline code can not access", name),
+            };
+
+            let value = match input {
+                syn::FnArg::Typed(typed) => input_value(&typed.ty, argument),
+                syn::FnArg::Receiver(_) => argument.clone(),
+            };
+            operands.push(quote!(in(#register) #value));
+        }
+
+        let clobber = match self.layout.convention {
+            Convention::SysV => "sysv64",
+            Convention::Microsoft => "win64",
+            Convention::Cdecl | Convention::Aapcs64 => unreachable!("Inline code is only assembled for x86-64"),
+        };
+
+        let ty = match &self.sig.output {
+            syn::ReturnType::Type(_, ty) => &**ty,
+            syn::ReturnType::Default => return quote! {
+                unsafe { ::core::arch::asm!(#template, #(#operands,)* clobber_abi(#clobber)); }
+            },
+        };
+
+        if let syn::Type::Never(_) = ty {
+            return quote! {
+                unsafe { ::core::arch::asm!(#template, #(#operands,)* options(noreturn)) }
+            };
+        }
+
+        let (out, ret) = output_value(ty);
+        quote! {
+            let ret: #out;
+            unsafe { ::core::arch::asm!(#template, #(#operands,)* lateout("rax") ret, clobber_abi(#clobber)); }
+            #ret
+        }
+    }
+}
+
+/// Convert an argument to a type that `asm!` accepts as a register operand.
+fn input_value(ty: &syn::Type, argument: &TokenStream) -> TokenStream {
+    match ty {
+        syn::Type::Reference(reference) if reference.mutability.is_some() => quote!(#argument as *mut _),
+        syn::Type::Reference(_) => quote!(#argument as *const _),
+        syn::Type::Paren(paren) => input_value(&paren.elem, argument),
+        syn::Type::Group(group) => input_value(&group.elem, argument),
+        // Narrow integers are not valid for a 64-bit register.
+        syn::Type::Path(path) if is_primitive(path) => quote!(#argument as u64),
+        _ => argument.clone(),
+    }
+}
+
+/// The type of the `rax` output and the conversion to the return type.
+fn output_value(ty: &syn::Type) -> (TokenStream, TokenStream) {
+    match ty {
+        syn::Type::Reference(reference) => {
+            let elem = &reference.elem;
+            match reference.mutability {
+                Some(_) => (quote!(*mut #elem), quote!(unsafe { &mut *ret })),
+                None => (quote!(*const #elem), quote!(unsafe { &*ret })),
+            }
+        },
+        syn::Type::Paren(paren) => output_value(&paren.elem),
+        syn::Type::Group(group) => output_value(&group.elem),
+        // Only the low byte of a returned `bool` is defined.
+        syn::Type::Path(path) if path.path.is_ident("bool") => (quote!(u64), quote!(ret as u8 != 0)),
+        syn::Type::Path(path) if path.path.is_ident("char") => {
+            (quote!(u64), quote!(unsafe { ::core::char::from_u32_unchecked(ret as u32) }))
+        },
+        syn::Type::Path(path) if is_primitive(path) => (quote!(u64), quote!(ret as #ty)),
+        ty => (quote!(#ty), quote!(ret)),
+    }
+}
+
+fn is_primitive(path: &syn::TypePath) -> bool {
+    const PRIMITIVES: [&str; 12] = [
+        "i8", "i16", "i32", "i64", "isize",
+        "u8", "u16", "u32", "u64", "usize",
+        "bool", "char",
+    ];
+
+    PRIMITIVES.iter().any(|primitive| path.path.is_ident(primitive))
+}
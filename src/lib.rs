@@ -1,102 +1,507 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::hash::{Hash, Hasher};
 use std::process;
 use std::io::Write;
 
 extern crate proc_macro;
 
-mod att;
-mod x86;
+mod abi;
+mod forward;
+mod frame;
+mod header;
+mod inline;
+mod symbol;
+mod unwind;
 
 use proc_macro::{Delimiter, Literal, Group, Punct, Spacing, TokenStream, TokenTree};
 use quote::{quote, ToTokens};
-use rand::{thread_rng, Rng};
+
+use abi::{Abi, Arch, Convention, Layout, Location};
+use forward::Forward;
+use frame::Frame;
+use header::Header;
+use inline::Inline;
+use symbol::Symbol;
+use unwind::Unwind;
 
 #[proc_macro_attribute]
 pub fn assemble(args: TokenStream, input: TokenStream) -> TokenStream {
     let attr = syn::parse_macro_input!(args as syn::AttributeArgs);
-    let mut assembler: Box<dyn Assembler> = choose_backed(&attr);
+    let options = Options::from_attr(&attr);
+    let mut assembler: Box<dyn Assembler> = choose_backed(&options);
 
     let (head, body) = split_function(input);
-    let asm_input = get_body(body);
+    let forward = options.associated || options.safe || options.mode == Mode::Inline || head.function_def.inputs
+        .iter()
+        .any(|input| matches!(input, syn::FnArg::Receiver(_)));
+    if forward {
+        Forward::check(&head.function_def);
+    }
 
+    let layout = Layout::classify(&head.function_def, head.abi, options.arch);
+    if options.registers_only {
+        layout.deny_stack();
+    }
 
-    let raw = assembler.assemble(&asm_input);
-    let len = raw.len();
-    let definition = {
-        let mut items = TokenStream::new();
-        for byte in &raw {
-            if !items.is_empty() {
-                items.extend(Some(TokenTree::Punct(Punct::new(',', Spacing::Alone))));
-            }
-            items.extend(Some(TokenTree::Literal(Literal::u8_unsuffixed(*byte))));
-        }
-        let tree = TokenTree::Group(Group::new(Delimiter::Bracket, items));
-        let stream = TokenStream::from(tree);
-        proc_macro2::TokenStream::from(stream)
-    };
+    let asm_input = get_body(body);
+    let mut asm_input = substitute_arguments(&asm_input, &layout, &options);
+    if let Some(frame) = &options.frame {
+        asm_input.text = frame.wrap(&asm_input.text, &options.backend);
+    }
 
-    let unique_name = choose_link_name();
-    let unique_ident = syn::Ident::new(&unique_name, proc_macro2::Span::call_site());
-    let mut binary_symbol = quote! {
-        mod #unique_ident {
-            #[repr(C)]
-            #[repr(align(16))]
-            struct T([u8; #len]);
-
-            #[link_section=".text"]
-            #[no_mangle]
-            static #unique_ident: T = T(#definition);
+    if options.mode == Mode::Inline {
+        let code = assembler.assemble(&Inline::strip_return(&asm_input.text));
+        let inline = Inline {
+            code: &code,
+            layout: &layout,
+            sig: &head.function_def,
+        };
+
+        let mut checks = head.abi.target_check(options.arch);
+        if asm_input.named_arguments {
+            checks.extend(head.abi.convention_check());
         }
+        checks.extend(layout.size_checks());
+
+        let forward = Forward {
+            attrs: &head.attrs,
+            visibility: &head.visibility,
+            sig: &head.function_def,
+            safe: options.safe,
+        };
+        let function = forward.wrap(|arguments| {
+            let body = inline.body(arguments);
+            quote!(#checks #body)
+        });
+        return function.into();
+    }
+
+    // Only the frames of x86-64 code are derived, other code needs explicit directives.
+    let derive = options.arch == Arch::X86_64;
+    let unwind = Unwind::new(&asm_input.text, derive);
+    let (raw, offsets) = assembler.assemble_with_offsets(&unwind.code);
+    let unwind = unwind.at_offsets(offsets.as_deref(), raw.len());
+
+    let unique_name = match &options.export_name {
+        Some(name) => name.clone(),
+        None => choose_link_name(&head.function_def.ident, &raw),
     };
+    let declaration = Forward::declaration(&head.function_def);
+    if let Some(header) = &options.c_header {
+        header.declare(&unique_name, &declaration, head.abi);
+    }
+    let symbol = Symbol {
+        link_name: &unique_name,
+        function: &head.function_def.ident,
+        section: options.section.as_deref(),
+        code: &raw,
+        unwind: unwind.as_deref(),
+    };
+    let mut binary_symbol = match options.mode {
+        _ if options.static_code() => symbol.static_definition(),
+        Mode::Symbol => symbol.definition(),
+        Mode::GlobalAsm => symbol.function_definition(),
+        Mode::Static => symbol.static_definition(),
+        Mode::Inline => unreachable!("Inline code has no symbol"),
+    };
+
+    let mut attrs: Vec<syn::Attribute> = vec![syn::parse_quote!(#[link_name=#unique_name])];
+    if options.listing {
+        attrs.extend(layout.listing().into_iter().map(|line| syn::parse_quote!(#[doc=#line])));
+    }
 
     let function_def = syn::ForeignItem::Fn(syn::ForeignItemFn {
-        attrs: vec![syn::parse_quote!(#[link_name=#unique_name])],
-        vis: head.visibility,
-        sig: head.function_def,
+        attrs,
+        // The declaration is private to the forwarding function.
+        vis: if forward { syn::Visibility::Inherited } else { head.visibility.clone() },
+        sig: declaration,
         semi_token: syn::token::Semi::default(),
     });
 
+    let abi = head.abi;
     let function_symbol = quote! {
-        extern "C" {
+        #abi {
             #function_def
         }
     };
 
     binary_symbol.extend(function_symbol);
+    binary_symbol.extend(head.abi.target_check(options.arch));
+    // All arguments are on the stack in the only convention of x86.
+    if asm_input.named_arguments && options.arch == Arch::X86_64 {
+        binary_symbol.extend(head.abi.convention_check());
+    }
+    binary_symbol.extend(layout.size_checks());
+
+    if forward {
+        let forward = Forward {
+            attrs: &head.attrs,
+            visibility: &head.visibility,
+            sig: &head.function_def,
+            safe: options.safe,
+        };
+        return forward.function(binary_symbol).into();
+    }
+
     binary_symbol.into()
 }
 
-fn choose_backed(attr: &[syn::NestedMeta]) -> Box<dyn Assembler> {
-    enum Backend {
-        GnuAs,
-        Nasm,
-        Dynasm,
-    }
-
-    let backend = match &attr {
-        [syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue { path , lit, .. }))] => {
-            if path.is_ident("backend") {
-                if let syn::Lit::Str(st) = lit {
-                    match st.value().as_str() {
-                        "nasm" => Backend::Nasm,
-                        "dynasm" => Backend::Dynasm,
-                        "gnu-as" | "gnuas" | "gas" | "as" => Backend::GnuAs,
-                        _ => panic!("Unknown backend (nasm, dynasm, gnuas, gnu-as, gas, as)"),
+/// The arguments of the attribute.
+struct Options {
+    backend: Backend,
+    /// The instruction set to assemble for.
+    arch: Arch,
+    /// Document the location of each argument on the generated declaration.
+    listing: bool,
+    /// Fail if any argument would be passed on the stack.
+    registers_only: bool,
+    /// The section to place the code in on ELF targets, instead of a default per function.
+    section: Option<String>,
+    /// A stack frame to set up around the body.
+    frame: Option<Frame>,
+    /// A fixed name for the symbol of the code.
+    export_name: Option<String>,
+    /// A header to declare the exported symbol in.
+    c_header: Option<Header>,
+    /// Generate an associated function forwarding to the declaration.
+    associated: bool,
+    /// Generate a safe function forwarding to the declaration.
+    safe: bool,
+    /// How the code is made callable.
+    mode: Mode,
+}
+
+#[derive(PartialEq, Eq)]
+enum Mode {
+    /// A symbol containing the code, called through a declaration.
+    ///
+    /// This is a function symbol where `global_asm!` can define one with type and size, on ELF
+    /// targets, and a static otherwise. A symbol with an `export_name` is always a static.
+    Symbol,
+    /// A function symbol defined with `global_asm!`, on all targets.
+    GlobalAsm,
+    /// A static aliased by the declaration, on all targets.
+    Static,
+    /// Inline assembly in a function that can be inlined.
+    Inline,
+}
+
+enum Backend {
+    GnuAs,
+    Nasm,
+    Dynasm,
+}
+
+impl Options {
+    fn from_attr(attr: &[syn::NestedMeta]) -> Self {
+        let mut options = Options {
+            backend: Backend::Dynasm,
+            arch: Arch::X86_64,
+            listing: false,
+            registers_only: false,
+            section: None,
+            frame: None,
+            export_name: None,
+            c_header: None,
+            associated: false,
+            safe: false,
+            mode: Mode::Symbol,
+        };
+
+        let mut frame_size = None;
+        let mut save = vec![];
+        for meta in attr {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue { path, lit, .. })) => {
+                    if path.is_ident("backend") {
+                        options.backend = Self::backend(lit);
+                    } else if path.is_ident("arch") {
+                        options.arch = Self::arch(lit);
+                    } else if path.is_ident("section") {
+                        options.section = Some(Self::section(lit));
+                    } else if path.is_ident("export_name") {
+                        options.export_name = Some(Self::export_name(lit));
+                    } else if path.is_ident("c_header") {
+                        options.c_header = Some(Self::c_header(lit));
+                    } else if path.is_ident("mode") {
+                        options.mode = Self::mode(lit);
+                    } else if path.is_ident("frame") {
+                        frame_size = Some(Self::frame_size(lit));
+                    } else {
+                        panic!("Unexpected keyword")
                     }
-                } else {
-                    panic!("Expected string value identifying backend");
-                }
-            } else {
-                panic!("Unexpected keyword")
+                },
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                    if path.is_ident("listing") {
+                        options.listing = true;
+                    } else if path.is_ident("registers_only") {
+                        options.registers_only = true;
+                    } else if path.is_ident("associated") {
+                        options.associated = true;
+                    } else if path.is_ident("safe") {
+                        options.safe = true;
+                    } else {
+                        panic!("Unexpected keyword")
+                    }
+                },
+                syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("save") => {
+                    save.extend(list.nested.iter().map(Self::saved_register));
+                },
+                _ => panic!("Unexpected attribute argument"),
             }
-        },
-        [] => Backend::Dynasm,
-        _ => panic!("Backend is unknown"),
+        }
+
+        if frame_size.is_some() || !save.is_empty() {
+            options.frame = Some(Frame::new(frame_size.unwrap_or(0), save));
+        }
+
+        if options.mode == Mode::Inline {
+            // There is no symbol and no frame of its own.
+            let symbol_options = [
+                ("section", options.section.is_some()),
+                ("export_name", options.export_name.is_some()),
+                ("c_header", options.c_header.is_some()),
+                ("frame", options.frame.is_some()),
+            ];
+            for (option, given) in &symbol_options {
+                assert!(!given, "The option `{}` can not be used with inline code", option);
+            }
+        }
+
+        match (options.arch, &options.backend) {
+            (Arch::X86_64, _) | (Arch::X86, Backend::GnuAs | Backend::Nasm) | (Arch::Aarch64, Backend::GnuAs) => {},
+            (Arch::X86, _) => panic!("The dynasm backend only assembles x86-64, use gnu-as or nasm for x86"),
+            (Arch::Aarch64, _) => panic!("Only the gnu-as backend assembles aarch64"),
+        }
+
+        if options.arch != Arch::X86_64 {
+            // Both generate x86-64 code around the body.
+            assert!(options.mode != Mode::Inline, "Inline code can only be assembled for x86-64");
+            assert!(options.frame.is_none(), "A `frame` can only be set up for x86-64");
+        }
+
+        if let (true, Some(name)) = (options.static_code(), &options.section) {
+            // A static is marked as data by the compiler regardless of its section. Only the
+            // default linker scripts make it executable, by merging these sections into the
+            // executable output. `global_asm!` flags the section itself, any name works there.
+            let executable = name == ".text" || name.starts_with(".text.");
+            assert!(executable, "Section `{}` of a static would not be executable, name it `.text.*`", name);
+        }
+
+        // The generated names are not stable enough to be written down elsewhere.
+        if options.c_header.is_some() && options.export_name.is_none() {
+            panic!("A `c_header` declaration requires a fixed `export_name`");
+        }
+
+        if options.c_header.is_some() && options.mode == Mode::GlobalAsm {
+            panic!("A `c_header` declaration requires an exported symbol, `global_asm` is not exported from a `cdylib`");
+        }
+
+        options
+    }
+
+    /// If the code is defined as a `static`.
+    ///
+    /// Only `#[no_mangle]` items are exported from a `cdylib`, a symbol of `global_asm!` is not.
+    /// A fixed name is meant to be called from elsewhere, by default it becomes a static.
+    fn static_code(&self) -> bool {
+        match self.mode {
+            Mode::Symbol => self.export_name.is_some(),
+            Mode::Static => true,
+            Mode::GlobalAsm | Mode::Inline => false,
+        }
+    }
+
+    fn arch(lit: &syn::Lit) -> Arch {
+        match lit {
+            syn::Lit::Str(st) => match st.value().as_str() {
+                "x86_64" => Arch::X86_64,
+                "x86" => Arch::X86,
+                "aarch64" => Arch::Aarch64,
+                _ => panic!("Unknown arch (x86_64, x86, aarch64)"),
+            },
+            _ => panic!("Expected string value identifying the arch"),
+        }
+    }
+
+    fn backend(lit: &syn::Lit) -> Backend {
+        if let syn::Lit::Str(st) = lit {
+            match st.value().as_str() {
+                "nasm" => Backend::Nasm,
+                "dynasm" => Backend::Dynasm,
+                "gnu-as" | "gnuas" | "gas" | "as" => Backend::GnuAs,
+                _ => panic!("Unknown backend (nasm, dynasm, gnuas, gnu-as, gas, as)"),
+            }
+        } else {
+            panic!("Expected string value identifying backend");
+        }
+    }
+}
+
+impl Backend {
+    /// Format a register as an operand for this backend.
+    fn register(&self, name: &str) -> String {
+        match self {
+            Backend::Dynasm => format!("%{}", name),
+            Backend::GnuAs | Backend::Nasm => name.to_string(),
+        }
+    }
+
+    /// Format the location of an argument as an operand for this backend.
+    ///
+    /// Stack arguments are addressed relative to the stack pointer on entry, the operand is wrong
+    /// after the body moved it. Within a generated frame they are addressed relative to the frame
+    /// pointer instead. The dynasm backend can not encode memory operands and rejects them:
+    ///
+    /// ```compile_fail
+    /// #[direct_asm::assemble]
+    /// unsafe extern "sysv64" fn seventh(_: u64, _: u64, _: u64, _: u64, _: u64, _: u64, g: u64) -> u64 {
+    ///     "mov %rax, {g}";
+    ///     "ret";
+    /// }
+    /// ```
+    fn operand(&self, location: &Location, frame: bool, convention: Convention) -> String {
+        let (base, offset) = match location {
+            Location::Register(reg) => return self.register(reg),
+            Location::Stack(_) if matches!(self, Backend::Dynasm) => {
+                panic!("The dynasm backend can not address arguments on the stack, use gnu-as or nasm")
+            },
+            // Below the arguments are the return address and the previous frame pointer.
+            Location::Stack(offset) if frame => ("rbp", offset + 8),
+            Location::Stack(offset) if convention == Convention::Aapcs64 => return format!("[sp, #{}]", offset),
+            Location::Stack(offset) => (convention.stack_pointer(), *offset),
+        };
+
+        let size = match convention.word_size() {
+            4 => "dword",
+            _ => "qword",
+        };
+        match self {
+            Backend::Dynasm => unreachable!("Stack operands are rejected for dynasm"),
+            Backend::Nasm => format!("{} [{}+{}]", size, base, offset),
+            Backend::GnuAs => format!("{} ptr [{}+{}]", size, base, offset),
+        }
+    }
+}
+
+/// Assemble a sequence of instructions into a byte array expression.
+///
+/// The input is written like the body of an assembled function, optionally prefixed by the
+/// `backend` and `arch` options of the attribute and a `;`. The result has type `[u8; N]` and can
+/// be used in constants.
+///
+/// ```ignore
+/// const EXIT: [u8; 9] = direct_asm::bytes!("mov %rax, 60"; "syscall");
+/// const NOP: &[u8] = &direct_asm::bytes!(backend = "nasm"; "nop");
+/// ```
+#[proc_macro]
+pub fn bytes(input: TokenStream) -> TokenStream {
+    let mut tokens = input.into_iter().peekable();
+
+    let mut attr = TokenStream::new();
+    if let Some(TokenTree::Ident(_)) = tokens.peek() {
+        attr.extend(tokens.by_ref().take_while(|token| match token {
+            TokenTree::Punct(punct) => punct.as_char() != ';',
+            _ => true,
+        }));
+    }
+
+    type AttributeList = syn::punctuated::Punctuated<syn::NestedMeta, syn::Token![,]>;
+    let attr = syn::parse_macro_input!(attr with AttributeList::parse_terminated);
+    // Everything else describes the symbol or the function around the code.
+    for meta in &attr {
+        if let syn::NestedMeta::Meta(meta) = meta {
+            let option = meta.path().get_ident().map(ToString::to_string).unwrap_or_default();
+            assert!(option == "backend" || option == "arch", "The option `{}` can not be used with `bytes!`", option);
+        }
+    }
+    let options = Options::from_attr(&attr.into_iter().collect::<Vec<_>>());
+    let mut assembler: Box<dyn Assembler> = choose_backed(&options);
+
+    let asm_input = get_instructions(tokens.collect());
+    let raw = assembler.assemble(&asm_input);
+    let len = raw.len();
+    let definition = byte_array(&raw);
+
+    let expression = quote! {
+        {
+            const BYTES: [u8; #len] = #definition;
+            BYTES
+        }
     };
 
-    match backend {
-        Backend::GnuAs => Box::new(GnuAs {}),
-        Backend::Nasm => Box::new(Nasm),
-        Backend::Dynasm => Box::new(x86::DynasmX86::new()),
+    expression.into()
+}
+
+impl Options {
+    fn frame_size(lit: &syn::Lit) -> u64 {
+        match lit {
+            syn::Lit::Int(int) => int.base10_parse().expect("Expected frame size in bytes"),
+            _ => panic!("Expected integer frame size in bytes"),
+        }
+    }
+
+    fn saved_register(meta: &syn::NestedMeta) -> String {
+        match meta {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) => match path.get_ident() {
+                Some(ident) => ident.to_string(),
+                None => panic!("Expected register name to save"),
+            },
+            _ => panic!("Expected register name to save"),
+        }
+    }
+
+    fn export_name(lit: &syn::Lit) -> String {
+        let name = match lit {
+            syn::Lit::Str(st) => st.value(),
+            _ => panic!("Expected string value naming the symbol"),
+        };
+
+        let valid = !name.is_empty()
+            && !name.starts_with(|ch: char| ch.is_ascii_digit())
+            && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+        assert!(valid, "`{}` is not a valid symbol name", name);
+        name
+    }
+
+    fn mode(lit: &syn::Lit) -> Mode {
+        match lit {
+            syn::Lit::Str(st) => match st.value().as_str() {
+                "symbol" => Mode::Symbol,
+                "global_asm" => Mode::GlobalAsm,
+                "static" => Mode::Static,
+                "inline" => Mode::Inline,
+                _ => panic!("Unknown mode (symbol, global_asm, static, inline)"),
+            },
+            _ => panic!("Expected string value identifying the mode"),
+        }
+    }
+
+    fn c_header(lit: &syn::Lit) -> Header {
+        match lit {
+            syn::Lit::Str(st) => Header { path: st.value() },
+            _ => panic!("Expected string value with the path of the header"),
+        }
+    }
+
+    fn section(lit: &syn::Lit) -> String {
+        match lit {
+            syn::Lit::Str(st) => st.value(),
+            _ => panic!("Expected string value naming the section"),
+        }
+    }
+}
+
+fn choose_backed(options: &Options) -> Box<dyn Assembler> {
+    match options.backend {
+        Backend::GnuAs => Box::new(GnuAs { arch: options.arch }),
+        Backend::Nasm => Box::new(Nasm { arch: options.arch }),
+        #[cfg(feature = "dynasm")]
+        Backend::Dynasm => Box::new(direct_asm_runtime::Assembler::new()),
+        #[cfg(not(feature = "dynasm"))]
+        Backend::Dynasm => panic!("The dynasm backend requires the `dynasm` feature"),
     }
 }
 
@@ -104,14 +509,18 @@ fn choose_backed(attr: &[syn::NestedMeta]) -> Box<dyn Assembler> {
 fn split_function(input: TokenStream) -> (Head, TokenStream) {
     let mut fn_item = syn::parse::<syn::ItemFn>(input)
         .expect("Must annotate a method definition");
-    // It should be declared as such because we put it into an `extern "C"` block ..
-    assert!(fn_item.sig.abi.is_some(), "Must specify function as having C abi");
+    // It should be declared as such because we put it into an `extern` block with that abi ..
+    let abi = fn_item.sig.abi.as_ref()
+        .map(Abi::from_declaration)
+        .expect("Must specify function as having an extern abi");
     // .. but remove it since the actual definition we output can not have it.
     fn_item.sig.abi = None;
     fn_item.sig.unsafety = None;
     let head = Head {
+        attrs: fn_item.attrs,
         function_def: fn_item.sig,
         visibility: fn_item.vis,
+        abi,
     };
     (head, fn_item.block.to_token_stream().into())
 }
@@ -125,6 +534,11 @@ fn get_body(block: TokenStream) -> String {
         _ => panic!("Expected function body"),
     };
 
+    get_instructions(body)
+}
+
+/// Join string literals separated by `;` into lines of assembly.
+fn get_instructions(body: TokenStream) -> String {
     let parts = body.into_iter().map(|item| match &item {
         TokenTree::Literal(literal) => {
             let stream = TokenTree::Literal(literal.clone()).into();
@@ -139,41 +553,155 @@ fn get_body(block: TokenStream) -> String {
     parts.collect()
 }
 
-/// Generate a random (196-bit) unique identifier for the symbol link in the proc macro.
+/// Format bytes as an array expression.
+fn byte_array(raw: &[u8]) -> proc_macro2::TokenStream {
+    let mut items = TokenStream::new();
+    for byte in raw {
+        if !items.is_empty() {
+            items.extend(Some(TokenTree::Punct(Punct::new(',', Spacing::Alone))));
+        }
+        items.extend(Some(TokenTree::Literal(Literal::u8_unsuffixed(*byte))));
+    }
+    let tree = TokenTree::Group(Group::new(Delimiter::Bracket, items));
+    let stream = TokenStream::from(tree);
+    proc_macro2::TokenStream::from(stream)
+}
+
+/// The assembly text after replacing argument names.
+struct Substituted {
+    text: String,
+    /// If any argument was referred to by its name.
+    named_arguments: bool,
+}
+
+/// Replace `{name}` with the register or stack slot of the argument `name`.
+///
+/// Literal braces are written as `{{` and `}}`, as in `format!`. The operand always refers to the
+/// full 64-bit register or eightbyte stack slot, regardless of the argument type.
+fn substitute_arguments(input: &str, layout: &Layout, options: &Options) -> Substituted {
+    let mut result = Substituted {
+        text: String::with_capacity(input.len()),
+        named_arguments: false,
+    };
+
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.text.push('}');
+            },
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&ch| ch != '}').collect();
+                let name = name.trim();
+                let location = layout.arguments
+                    .iter()
+                    .find(|(argument, _)| argument == name)
+                    .map(|(_, location)| location)
+                    .unwrap_or_else(|| panic!("No argument named `{}`", name));
+                let frame = options.frame.is_some();
+                result.text.push_str(&options.backend.operand(location, frame, layout.convention));
+                result.named_arguments = true;
+            },
+            '}' => panic!("Unmatched closing brace in body, double it for a literal brace"),
+            other => result.text.push(other),
+        }
+    }
+
+    result
+}
+
+/// Choose a readable and unique name for the symbol link in the proc macro.
 ///
 /// To execute the trick of re-interpreting a byte stream as a function we must choose a common
 /// link name between the symbol and the later function definition that imports that symbol. This
-/// should not collide with other defined symbols, as that might silently be unsafe.
-fn choose_link_name() -> String {
-    const CHOICES: &[u8; 64] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ZZ";
-    let mut randoms = [0u8; 32];
-    thread_rng().fill(&mut randoms);
+/// should not collide with other defined symbols, as that might silently be unsafe. The name
+/// contains the crate, module and function name followed by a hash of the definition site and
+/// the code, for example `_direct_asm_syscall_linux_raw_x86_64_call3_2f6c1d0e9a4b3c87`.
+fn choose_link_name(function: &syn::Ident, code: &[u8]) -> String {
+    let span = proc_macro::Span::call_site();
+    let krate = env::var("CARGO_CRATE_NAME").unwrap_or_default();
+    let file = span.file();
+
+    let mut hasher = DefaultHasher::new();
+    // Different versions of one crate may be linked together.
+    env::var("CARGO_PKG_VERSION").unwrap_or_default().hash(&mut hasher);
+    krate.hash(&mut hasher);
+    file.hash(&mut hasher);
+    span.line().hash(&mut hasher);
+    span.column().hash(&mut hasher);
+    code.hash(&mut hasher);
+
+    let mut name = String::from("_direct_asm");
+    for part in Some(krate.as_str()).into_iter().chain(module_path(&file, &krate)) {
+        if !part.is_empty() {
+            name.push('_');
+            name.extend(part.chars().map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' }));
+        }
+    }
 
-    let random = randoms
+    format!("{}_{}_{:016x}", name, function, hasher.finish())
+}
+
+/// Approximate the module path from the path of the source file.
+fn module_path<'a>(file: &'a str, krate: &str) -> Vec<&'a str> {
+    let components = file
+        .split(['/', '\\'])
+        .map(|part| part.trim_end_matches(".rs"))
+        .collect::<Vec<_>>();
+
+    // Everything up to the source directory is not part of the path.
+    let start = components
         .iter()
-        .map(|idx| usize::from(idx & 63))
-        .map(|idx| std::char::from_u32(CHOICES[idx].into()).unwrap())
-        .collect::<String>();
+        .position(|&part| ["src", "tests", "examples", "benches"].contains(&part))
+        .map_or(0, |idx| idx + 1);
+    let mut modules = components[start..].to_vec();
+
+    // Files that are the root of their module or crate.
+    if let Some(&last) = modules.last() {
+        if ["lib", "main", "mod"].contains(&last) || (modules.len() == 1 && last.replace('-', "_") == krate) {
+            modules.pop();
+        }
+    }
 
-    format!("_direct_asm_{}", random)
+    modules
 }
 
 struct Head {
+    /// Attributes of the function, kept only on a forwarding function.
+    attrs: Vec<syn::Attribute>,
     function_def: syn::Signature,
     visibility: syn::Visibility,
+    abi: Abi,
 }
 
 trait Assembler {
     fn assemble(&mut self, input: &str) -> Vec<u8>;
+
+    /// Assemble, also returning the offset at which each line starts if the backend can tell.
+    fn assemble_with_offsets(&mut self, input: &str) -> (Vec<u8>, Option<Vec<usize>>) {
+        (self.assemble(input), None)
+    }
 }
 
-struct Nasm;
+struct Nasm {
+    arch: Arch,
+}
 
 struct GnuAs {
+    arch: Arch,
 }
 
-fn nasmify(input: &str) -> Vec<u8> {
-    let input = format!("[BITS 64]\n{}", input);
+fn nasmify(input: &str, arch: Arch) -> Vec<u8> {
+    let bits = match arch {
+        Arch::X86 => 32,
+        _ => 64,
+    };
+    let input = format!("[BITS {}]\n{}", bits, input);
     std::fs::write("target/indirection.in", &input).unwrap();
 
     let mut nasm = process::Command::new("nasm")
@@ -196,14 +724,40 @@ fn nasmify(input: &str) -> Vec<u8> {
     output.stdout
 }
 
+#[cfg(feature = "dynasm")]
+impl Assembler for direct_asm_runtime::Assembler {
+    fn assemble(&mut self, input: &str) -> Vec<u8> {
+        direct_asm_runtime::Assembler::assemble(self, input)
+            .unwrap_or_else(|err| panic!("Dynasm failed: {}", err))
+    }
+
+    fn assemble_with_offsets(&mut self, input: &str) -> (Vec<u8>, Option<Vec<usize>>) {
+        let (code, offsets) = direct_asm_runtime::Assembler::assemble_with_offsets(self, input)
+            .unwrap_or_else(|err| panic!("Dynasm failed: {}", err));
+        (code, Some(offsets))
+    }
+}
+
 impl Assembler for Nasm {
     fn assemble(&mut self, input: &str) -> Vec<u8> {
-        nasmify(input)
+        nasmify(input, self.arch)
     }
 }
 
-impl Assembler for GnuAs {
-    fn assemble(&mut self, original_input: &str) -> Vec<u8> {
+impl GnuAs {
+    const ASSEMBLED_FILE: &'static str = "target/gnu-as.out";
+    const LINE_LABEL: &'static str = "_direct_asm_line_";
+
+    /// The binutils program for the architecture, those of the host only handle x86.
+    fn tool(&self, name: &str) -> String {
+        match self.arch {
+            Arch::X86_64 | Arch::X86 => name.to_string(),
+            Arch::Aarch64 => format!("aarch64-linux-gnu-{}", name),
+        }
+    }
+
+    /// Assemble the input into an object file at `ASSEMBLED_FILE`.
+    fn object(&self, original_input: &str) {
         let newlined;
         let input: &str;
 
@@ -214,19 +768,32 @@ impl Assembler for GnuAs {
             input = original_input;
         }
 
-        const ASSEMBLED_FILE: &str = "target/gnu-as.out";
         // Some arguments for reference:
         // target selection: -march=<name>
         // --32, --64, --x32 for isa qualification
         // -n do not optimize alignment
         // -mmnemonic/-msyntax=[att|intel]
-        let mut as_ = process::Command::new("as")
-            // We act as if this was safe, the least we can do is check thoroughly.
-            .arg("-msse-check=error")
-            .arg("-moperand-check=error")
-            .arg("-mmnemonic=intel")
-            .arg("-msyntax=intel")
-            .args(&["-o", ASSEMBLED_FILE])
+        let mut as_ = process::Command::new(self.tool("as"));
+        let isa = match self.arch {
+            Arch::X86_64 => Some("--64"),
+            Arch::X86 => Some("--32"),
+            // A separate assembler, with the syntax of the architecture reference.
+            Arch::Aarch64 => None,
+        };
+
+        if let Some(isa) = isa {
+            as_.arg(isa)
+                // We act as if this was safe, the least we can do is check thoroughly.
+                .arg("-msse-check=error")
+                .arg("-moperand-check=error")
+                .arg("-mmnemonic=intel")
+                .arg("-msyntax=intel")
+                // Registers are written without a `%` prefix, as in nasm.
+                .arg("-mnaked-reg");
+        }
+
+        let mut as_ = as_
+            .args(&["-o", Self::ASSEMBLED_FILE])
             .stdin(process::Stdio::piped())
             .stdout(process::Stdio::piped())
             .stderr(process::Stdio::piped())
@@ -241,17 +808,64 @@ impl Assembler for GnuAs {
         if !output.status.success() || !output.stderr.is_empty() {
             panic!("Gnu As failed: {}", String::from_utf8_lossy(&output.stderr));
         }
+    }
 
+    /// The offsets of all symbols in the object file.
+    fn symbols(&self) -> HashMap<String, usize> {
+        let output = process::Command::new(self.tool("nm"))
+            .arg(Self::ASSEMBLED_FILE)
+            .output()
+            .expect("Failed to spawn `nm`");
+        assert!(output.status.success(), "`nm` failed");
+
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let value = usize::from_str_radix(parts.next()?, 16).ok()?;
+                let name = parts.nth(1)?;
+                Some((name.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// The code of the object file.
+    fn binary(&self) -> Vec<u8> {
         // gnu as will always output ELF. We only need the binary from it. Better hope you didn't
         // use any tables or so, as those will be dropped in the process.
         // TODO: fail loudly.
-        let status = process::Command::new("objcopy")
+        let status = process::Command::new(self.tool("objcopy"))
             .args(&["-O", "binary"])
-            .arg(ASSEMBLED_FILE)
+            .arg(Self::ASSEMBLED_FILE)
             .status()
             .expect("Failed to spawn `objcopy`");
         assert!(status.success(), "`objcopy` failed");
 
-        std::fs::read(ASSEMBLED_FILE).expect("No output produced")
+        std::fs::read(Self::ASSEMBLED_FILE).expect("No output produced")
+    }
+}
+
+impl Assembler for GnuAs {
+    fn assemble(&mut self, input: &str) -> Vec<u8> {
+        self.object(input);
+        self.binary()
+    }
+
+    fn assemble_with_offsets(&mut self, input: &str) -> (Vec<u8>, Option<Vec<usize>>) {
+        // Label the start of each line, the symbol table then contains their offsets.
+        let lines = input.lines().count();
+        let labeled = input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| format!("{}{}:\n{}\n", Self::LINE_LABEL, idx, line))
+            .collect::<String>();
+
+        self.object(&labeled);
+        let symbols = self.symbols();
+        let offsets = (0..lines)
+            .map(|idx| symbols[&format!("{}{}", Self::LINE_LABEL, idx)])
+            .collect();
+
+        (self.binary(), Some(offsets))
     }
 }
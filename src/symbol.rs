@@ -0,0 +1,198 @@
+//! Definition of the symbol containing the assembled code.
+//!
+//! On ELF targets the code is emitted with `global_asm!` as a proper function symbol, with type
+//! and size, so that debuggers and profilers attribute addresses to it. Other object formats fall
+//! back to a `static` in an executable section, which the declaration aliases by its link name.
+//! Either can be chosen for all targets instead, where `global_asm!` uses the directives of each
+//! object format. Only function symbols on ELF and Mach-O have call frame information.
+use quote::quote;
+use proc_macro2::TokenStream;
+
+pub struct Symbol<'a> {
+    /// The link name shared by the definition and the declaration.
+    pub link_name: &'a str,
+    /// The name of the assembled function, for the default section.
+    pub function: &'a syn::Ident,
+    /// A section chosen by the user instead of the default, on ELF targets only.
+    pub section: Option<&'a str>,
+    pub code: &'a [u8],
+    /// Call frame directives at byte offsets into the code, or `None` to omit the frame entry.
+    pub unwind: Option<&'a [(usize, String)]>,
+}
+
+impl Symbol<'_> {
+    /// All items defining the symbol, for all targets.
+    pub fn definition(&self) -> TokenStream {
+        let elf = elf_cfg();
+        let global = self.global_asm();
+        let fallback = self.static_definition();
+        quote! {
+            #[cfg(#elf)]
+            #global
+
+            #[cfg(not(#elf))]
+            #fallback
+        }
+    }
+
+    /// A function symbol for all targets.
+    pub fn function_definition(&self) -> TokenStream {
+        let elf = elf_cfg();
+        let global = self.global_asm();
+        let mach_o = self.mach_o_global_asm();
+        let coff = self.coff_global_asm();
+        quote! {
+            #[cfg(#elf)]
+            #global
+
+            #[cfg(target_vendor = "apple")]
+            #mach_o
+
+            #[cfg(any(windows, target_os = "uefi"))]
+            #coff
+        }
+    }
+
+    fn elf_section(&self) -> String {
+        match self.section {
+            Some(section) => section.to_string(),
+            None => format!(".text.direct_asm.{}", self.function),
+        }
+    }
+
+    /// A function symbol with the code as its content.
+    fn global_asm(&self) -> TokenStream {
+        let name = self.link_name;
+        // The `%` prefix of types is understood on all targets, `@` starts a comment on ARM.
+        let mut lines = vec![
+            format!(".pushsection {},\"ax\",%progbits", self.elf_section()),
+            ".p2align 4".to_string(),
+            format!(".globl {}", name),
+            format!(".type {},%function", name),
+            format!("{}:", name),
+        ];
+
+        self.push_code(&mut lines);
+        lines.push(format!(".size {}, . - {}", name, name));
+        lines.push(".popsection".to_string());
+        self.module(lines)
+    }
+
+    /// A function symbol in a Mach-O object, where C symbols have a leading underscore.
+    fn mach_o_global_asm(&self) -> TokenStream {
+        let name = format!("_{}", self.link_name);
+        let mut lines = vec![
+            ".pushsection __TEXT,__text,regular,pure_instructions".to_string(),
+            ".p2align 4".to_string(),
+            format!(".globl {}", name),
+            format!("{}:", name),
+        ];
+
+        self.push_code(&mut lines);
+        lines.push(".popsection".to_string());
+        self.module(lines)
+    }
+
+    /// A function symbol in a COFF object.
+    ///
+    /// Unwinding through it would need `.seh_*` directives, it gets no unwind information.
+    fn coff_global_asm(&self) -> TokenStream {
+        let name = self.link_name;
+        let mut lines = vec![
+            ".section .text$direct_asm,\"xr\"".to_string(),
+            ".p2align 4".to_string(),
+            format!(".globl {}", name),
+            format!(".def {}; .scl 2; .type 32; .endef", name),
+            format!("{}:", name),
+        ];
+
+        if !self.code.is_empty() {
+            lines.push(byte_directive(self.code));
+        }
+        // There is no section stack, the assembler expects to be left in `.text`.
+        lines.push(".text".to_string());
+        self.module(lines)
+    }
+
+    /// The code, surrounded by call frame information if there is any.
+    fn push_code(&self, lines: &mut Vec<String>) {
+        match self.unwind {
+            Some(directives) => {
+                lines.push(".cfi_startproc".to_string());
+                let mut start = 0;
+                for (offset, directive) in directives {
+                    if start < *offset {
+                        lines.push(byte_directive(&self.code[start..*offset]));
+                        start = *offset;
+                    }
+                    lines.push(directive.clone());
+                }
+                if start < self.code.len() {
+                    lines.push(byte_directive(&self.code[start..]));
+                }
+                lines.push(".cfi_endproc".to_string());
+            },
+            None if !self.code.is_empty() => lines.push(byte_directive(self.code)),
+            None => {},
+        }
+    }
+
+    /// The `global_asm!` of the lines.
+    fn module(&self, lines: Vec<String>) -> TokenStream {
+        // In a module, as `global_asm!` is not allowed in the body of a forwarding function.
+        let unique_ident = syn::Ident::new(self.link_name, proc_macro2::Span::call_site());
+        quote! {
+            mod #unique_ident {
+                ::core::arch::global_asm!(#(#lines),*);
+            }
+        }
+    }
+
+    /// A static with the code as its value.
+    pub fn static_definition(&self) -> TokenStream {
+        let unique_ident = syn::Ident::new(self.link_name, proc_macro2::Span::call_site());
+        let len = self.code.len();
+        let definition = crate::byte_array(self.code);
+        let link_section = self.section_attributes();
+        quote! {
+            mod #unique_ident {
+                #[repr(C)]
+                #[repr(align(16))]
+                struct T([u8; #len]);
+
+                #link_section
+                #[no_mangle]
+                static #unique_ident: T = T(#definition);
+            }
+        }
+    }
+
+    /// The `link_section` of the static.
+    ///
+    /// By default each function gets its own section so that unused code can be removed by the
+    /// linker. On Mach-O the section attributes can be given directly, on ELF and COFF the section
+    /// of a static is always flagged as data but merged into the executable `.text` by the linker.
+    /// The section names differ between the formats, one chosen by the user only applies to ELF.
+    fn section_attributes(&self) -> TokenStream {
+        let elf = elf_cfg();
+        let elf_section = self.elf_section();
+        quote! {
+            #[cfg_attr(#elf, link_section = #elf_section)]
+            #[cfg_attr(target_vendor = "apple", link_section = "__TEXT,__text,regular,pure_instructions")]
+            #[cfg_attr(any(windows, target_os = "uefi"), link_section = ".text$direct_asm")]
+        }
+    }
+}
+
+/// The configuration predicate of targets producing ELF objects.
+fn elf_cfg() -> TokenStream {
+    quote!(not(any(target_vendor = "apple", windows, target_os = "uefi")))
+}
+
+pub fn byte_directive(code: &[u8]) -> String {
+    let bytes = code
+        .iter()
+        .map(|byte| format!("{:#04x}", byte))
+        .collect::<Vec<_>>();
+    format!(".byte {}", bytes.join(","))
+}
@@ -0,0 +1,243 @@
+//! Call frame information, such that unwinders can walk through assembled functions.
+//!
+//! The body may contain `.cfi_*` directives which are passed through verbatim. Otherwise the
+//! directives are derived from the instructions that move the stack pointer in a straight-line
+//! prologue and epilogue: `push`, `pop`, `sub rsp`, `add rsp`, `mov rbp, rsp` and `leave`. A
+//! function with multiple exits or other stack manipulation needs explicit directives. Following
+//! the lines in order only describes one path, so a body with jumps or several returns that moves
+//! the stack pointer is rejected rather than given wrong information. A body that writes the stack
+//! pointer in any other way, such as `and rsp, -16`, gets no call frame information at all. The
+//! same holds for code of other architectures, which is not derived.
+
+/// The body split into code and call frame directives.
+pub struct Unwind {
+    /// The lines of code, without directives.
+    pub code: String,
+    /// Directives with the index of the code line they precede.
+    pub directives: Vec<(usize, String)>,
+    /// If the directives were written in the body or generated for a frame.
+    pub explicit: bool,
+    /// If the directives describe every move of the stack pointer.
+    pub described: bool,
+}
+
+/// The state of the call frame while following the instructions.
+struct Frame {
+    /// Distance from the stack pointer to the canonical frame address.
+    offset: i64,
+    /// The offset when the frame pointer was set up, if it is the base of the frame.
+    frame_pointer: Option<i64>,
+    /// Registers whose value was saved on the stack.
+    saved: Vec<u8>,
+}
+
+impl Unwind {
+    /// Split the directives from the code.
+    ///
+    /// Without explicit directives they are derived from the code if `derive` is set, otherwise the
+    /// moves of the stack pointer are unknown and no frame information is given.
+    pub fn new(input: &str, derive: bool) -> Self {
+        let mut unwind = Unwind {
+            code: String::with_capacity(input.len()),
+            directives: vec![],
+            explicit: false,
+            described: true,
+        };
+
+        let mut lines = 0;
+        for line in input.lines() {
+            if line.trim_start().starts_with(".cfi_") {
+                unwind.directives.push((lines, line.trim().to_string()));
+                unwind.explicit = true;
+            } else {
+                unwind.code.push_str(line);
+                unwind.code.push('\n');
+                lines += 1;
+            }
+        }
+
+        if !unwind.explicit {
+            let derived = if derive { self::derive(&unwind.code) } else { None };
+            match derived {
+                Some(directives) => unwind.directives = directives,
+                None => unwind.described = false,
+            }
+            if !unwind.directives.is_empty() && !straight_line(&unwind.code) {
+                panic!("Call frame information can not be derived for a body with jumps or several returns, write `.cfi_*` directives");
+            }
+        }
+
+        unwind
+    }
+
+    /// Place the directives at the byte offsets of the lines they precede.
+    ///
+    /// Returns `None` if no frame information can be given because the stack pointer is moved in
+    /// ways the directives do not describe, or the backend did not report the offsets of lines. No
+    /// information is better than wrong information. Explicit directives can not be dropped
+    /// silently, they are an error with such a backend.
+    pub fn at_offsets(self, offsets: Option<&[usize]>, len: usize) -> Option<Vec<(usize, String)>> {
+        let offsets = match offsets {
+            _ if !self.described => return None,
+            Some(offsets) => offsets,
+            None if self.explicit => panic!("This backend can not place `.cfi_*` directives, use gnu-as or dynasm"),
+            None if self.directives.is_empty() => return Some(vec![]),
+            None => return None,
+        };
+
+        let directives = self.directives
+            .into_iter()
+            .map(|(line, directive)| (offsets.get(line).copied().unwrap_or(len), directive))
+            .collect();
+        Some(directives)
+    }
+}
+
+/// The directives for the moves of the stack pointer, `None` if one is not understood.
+fn derive(code: &str) -> Option<Vec<(usize, String)>> {
+    let mut frame = Frame {
+        // Only the return address.
+        offset: 8,
+        frame_pointer: None,
+        saved: vec![],
+    };
+
+    let mut directives = vec![];
+    for (idx, line) in code.lines().enumerate() {
+        for directive in frame.step(line)? {
+            // The new frame state applies after the instruction.
+            directives.push((idx + 1, directive));
+        }
+    }
+
+    Some(directives)
+}
+
+/// If the code has no jumps and at most one return, such that it is executed in order.
+fn straight_line(code: &str) -> bool {
+    let mnemonics = code.lines().filter_map(|line| {
+        line.split_whitespace()
+            .filter(|part| !part.ends_with(':'))
+            .find(|part| !["rep", "repz", "repe", "bnd", "notrack"].contains(part))
+            .map(str::to_lowercase)
+    });
+
+    let mut returns = 0;
+    for mnemonic in mnemonics {
+        if mnemonic.starts_with('j') || mnemonic.starts_with("loop") {
+            return false;
+        }
+        if mnemonic == "ret" {
+            returns += 1;
+        }
+    }
+
+    returns <= 1
+}
+
+impl Frame {
+    /// The directives after the instruction, `None` if it moves the stack pointer otherwise.
+    fn step(&mut self, line: &str) -> Option<Vec<String>> {
+        let parts = line
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .filter(|part| !part.ends_with(':'))
+            .map(|part| part.trim_start_matches(['%', '$']).to_lowercase())
+            .collect::<Vec<_>>();
+        let parts = parts.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let directives = match parts.as_slice() {
+            ["push", reg] => match dwarf_register(reg) {
+                Some(number) => {
+                    let mut directives = self.adjust(8);
+                    directives.push(format!(".cfi_offset {}, {}", number, -self.offset));
+                    self.saved.push(number);
+                    directives
+                },
+                None => self.adjust(8),
+            },
+            ["pop", "rsp"] => return None,
+            ["pop", reg] => {
+                let number = dwarf_register(reg);
+                let mut directives = vec![];
+                if number == Some(RBP) && self.frame_pointer.is_some() {
+                    // The frame pointer is gone, describe the frame by the stack pointer again.
+                    self.frame_pointer = None;
+                    self.offset -= 8;
+                    directives.push(format!(".cfi_def_cfa {}, {}", RSP, self.offset));
+                } else {
+                    directives.extend(self.adjust(-8));
+                }
+                if let Some(number) = number.filter(|number| self.saved.contains(number)) {
+                    self.saved.retain(|&saved| saved != number);
+                    directives.push(format!(".cfi_restore {}", number));
+                }
+                directives
+            },
+            ["sub", "rsp", amount] => self.adjust(immediate(amount)?),
+            ["add", "rsp", amount] => self.adjust(-immediate(amount)?),
+            ["mov", "rsp", "rbp"] => {
+                self.offset = self.frame_pointer?;
+                vec![]
+            },
+            ["mov", "rbp", "rsp"] => {
+                self.frame_pointer = Some(self.offset);
+                vec![format!(".cfi_def_cfa_register {}", RBP)]
+            },
+            ["leave"] => {
+                self.offset = self.frame_pointer.take()? - 8;
+                self.saved.retain(|&saved| saved != RBP);
+                vec![
+                    format!(".cfi_def_cfa {}, {}", RSP, self.offset),
+                    format!(".cfi_restore {}", RBP),
+                ]
+            },
+            [mnemonic, operands @ ..] if writes_stack_pointer(mnemonic, operands) => return None,
+            _ => vec![],
+        };
+
+        Some(directives)
+    }
+
+    /// Move the stack pointer by `amount` bytes towards lower addresses.
+    fn adjust(&mut self, amount: i64) -> Vec<String> {
+        self.offset += amount;
+        match self.frame_pointer {
+            // The frame address is relative to the frame pointer, unaffected by the stack pointer.
+            Some(_) => vec![],
+            None => vec![format!(".cfi_adjust_cfa_offset {}", amount)],
+        }
+    }
+}
+
+/// If an instruction not understood by `step` changes the stack pointer.
+fn writes_stack_pointer(mnemonic: &str, operands: &[&str]) -> bool {
+    const STACK_POINTER: [&str; 4] = ["rsp", "esp", "sp", "spl"];
+    const IMPLICIT: [&str; 10] = [
+        "push", "pushq", "pushf", "pushfq", "pop", "popq", "popf", "popfq", "enter", "leave",
+    ];
+
+    IMPLICIT.contains(&mnemonic)
+        || operands.first().is_some_and(|operand| STACK_POINTER.contains(operand))
+        || mnemonic == "xchg" && operands.iter().any(|operand| STACK_POINTER.contains(operand))
+}
+
+pub const RBP: u8 = 6;
+pub const RSP: u8 = 7;
+
+/// The DWARF register number of a 64-bit general purpose register.
+pub fn dwarf_register(name: &str) -> Option<u8> {
+    const REGISTERS: [&str; 16] = [
+        "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp",
+        "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+    ];
+
+    REGISTERS.iter().position(|&reg| reg == name).map(|idx| idx as u8)
+}
+
+fn immediate(value: &str) -> Option<i64> {
+    match value.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
@@ -0,0 +1,36 @@
+//! Generate the `SysNr` constants of each architecture from the vendored tables.
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const TABLES: [&str; 5] = ["x86_64", "i386", "x32", "aarch64", "riscv64"];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").expect("Cargo sets the output directory");
+    println!("cargo:rerun-if-changed=tables");
+
+    for arch in &TABLES {
+        let path = format!("tables/{}.tbl", arch);
+        let table = fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err));
+
+        let mut code = String::from("impl SysNr {\n");
+        for line in table.lines().filter(|line| !line.starts_with('#')) {
+            let mut columns = line.split_whitespace();
+            let (nr, name) = match (columns.next(), columns.next()) {
+                (Some(nr), Some(name)) => (nr, name),
+                _ => panic!("Malformed line in {}: {}", path, line),
+            };
+
+            let nr: isize = nr.parse()
+                .unwrap_or_else(|err| panic!("Malformed number in {}: {}", path, err));
+            writeln!(code, "    /// `{}`", name).unwrap();
+            writeln!(code, "    pub const {}: SysNr = SysNr({});", name.to_uppercase(), nr).unwrap();
+        }
+        code.push_str("}\n");
+
+        fs::write(Path::new(&out_dir).join(format!("{}.rs", arch)), code)
+            .expect("Failed to write the generated constants");
+    }
+}
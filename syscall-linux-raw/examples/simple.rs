@@ -2,24 +2,19 @@
 #![no_std]
 #![no_main]
 
-fn exit() -> ! {
-    unsafe {
-        syscall_linux_raw::syscall1(syscall_linux_raw::SysNr(60), 0);
-        core::hint::unreachable_unchecked();
-    }
-}
+use syscall_linux_raw::calls::{self, Fd};
+use syscall_linux_raw::start::{Args, Auxv, Env};
 
-fn write(fd: usize, buf: &[u8]) {
-    unsafe {
-        syscall_linux_raw::syscall3(syscall_linux_raw::SysNr(1), fd as isize, buf.as_ptr() as isize, buf.len() as isize);
+fn main(args: Args, _: Env, _: Auxv) -> i32 {
+    let _ = calls::write(Fd::STDOUT, b"Hello, world!\n");
+    for arg in args.iter() {
+        let _ = calls::write(Fd::STDOUT, arg.to_bytes());
+        let _ = calls::write(Fd::STDOUT, b"\n");
     }
+    0
 }
 
-#[no_mangle]
-pub fn _start() -> ! {
-    write(1, "Hello, world!\n".as_bytes());
-    exit();
-}
+syscall_linux_raw::entry!(main);
 
 use core::panic::PanicInfo;
 
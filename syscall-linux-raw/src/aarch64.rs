@@ -0,0 +1,53 @@
+use super::SysNr;
+
+// The call number is in `x8`, the arguments in `x0` to `x5` and the result in `x0`. We take the
+// number as the argument after the others, that is the next register.
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call0(nr: SysNr) -> isize {
+    "mov x8, x0";
+    "svc #0";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call1(a: isize, nr: SysNr) -> isize {
+    "mov x8, x1";
+    "svc #0";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call2(_: isize, _: isize, nr: SysNr) -> isize {
+    "mov x8, x2";
+    "svc #0";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call3(_: isize, _: isize, _: isize, nr: SysNr) -> isize {
+    "mov x8, x3";
+    "svc #0";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call4(_: isize, _: isize, _: isize, _: isize, nr: SysNr) -> isize {
+    "mov x8, x4";
+    "svc #0";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call5(_: isize, _: isize, _: isize, _: isize, _: isize, nr: SysNr) -> isize {
+    "mov x8, x5";
+    "svc #0";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "aarch64")]
+pub unsafe extern "C" fn call6(_: isize, _: isize, _: isize, _: isize, _: isize, _: isize, nr: SysNr) -> isize {
+    "mov x8, x6";
+    "svc #0";
+    "ret";
+}
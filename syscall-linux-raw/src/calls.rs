@@ -0,0 +1,196 @@
+//! Typed wrappers of common system calls.
+//!
+//! These only translate arguments and decode errors. Calls that are sound for all arguments are
+//! safe, those that can invalidate memory or file descriptors owned by others are not. Flags are
+//! passed through unchecked, the constants are those of the generic table shared by x86, arm64
+//! and riscv.
+use core::ffi::CStr;
+
+use crate::{syscall1, syscall2, syscall3, syscall4, syscall6, Errno, SysNr};
+
+/// A file descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Fd(pub i32);
+
+impl Fd {
+    pub const STDIN: Fd = Fd(0);
+    pub const STDOUT: Fd = Fd(1);
+    pub const STDERR: Fd = Fd(2);
+}
+
+/// Resolve relative paths from the working directory.
+const AT_FDCWD: isize = -100;
+
+/// The unit of the offset of `mmap2`, 4096 bytes regardless of the page size.
+#[cfg(target_arch = "x86")]
+const MMAP2_OFFSET_UNIT: usize = 4096;
+
+pub const O_RDONLY: i32 = 0o0;
+pub const O_WRONLY: i32 = 0o1;
+pub const O_RDWR: i32 = 0o2;
+pub const O_CREAT: i32 = 0o100;
+pub const O_EXCL: i32 = 0o200;
+pub const O_TRUNC: i32 = 0o1000;
+pub const O_APPEND: i32 = 0o2000;
+pub const O_NONBLOCK: i32 = 0o4000;
+pub const O_CLOEXEC: i32 = 0o2000000;
+
+pub const PROT_NONE: i32 = 0x0;
+pub const PROT_READ: i32 = 0x1;
+pub const PROT_WRITE: i32 = 0x2;
+pub const PROT_EXEC: i32 = 0x4;
+
+pub const MAP_SHARED: i32 = 0x01;
+pub const MAP_PRIVATE: i32 = 0x02;
+pub const MAP_FIXED: i32 = 0x10;
+pub const MAP_ANONYMOUS: i32 = 0x20;
+
+pub const CLONE_VM: i32 = 0x100;
+pub const CLONE_FS: i32 = 0x200;
+pub const CLONE_FILES: i32 = 0x400;
+pub const CLONE_SIGHAND: i32 = 0x800;
+pub const CLONE_THREAD: i32 = 0x10000;
+pub const CLONE_SYSVSEM: i32 = 0x40000;
+pub const CLONE_SETTLS: i32 = 0x80000;
+/// The flags of a thread sharing everything with the caller, as in `pthread_create`.
+pub const CLONE_THREAD_FLAGS: i32 = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND
+    | CLONE_THREAD | CLONE_SYSVSEM;
+
+pub fn read(fd: Fd, buf: &mut [u8]) -> Result<usize, Errno> {
+    Errno::result(unsafe {
+        syscall3(SysNr::READ, fd.0 as isize, buf.as_mut_ptr() as isize, buf.len() as isize)
+    })
+}
+
+pub fn write(fd: Fd, buf: &[u8]) -> Result<usize, Errno> {
+    Errno::result(unsafe {
+        syscall3(SysNr::WRITE, fd.0 as isize, buf.as_ptr() as isize, buf.len() as isize)
+    })
+}
+
+/// Open a file, relative to the working directory.
+///
+/// This is `openat`, as some architectures have no `open`.
+pub fn open(path: &CStr, flags: i32, mode: u32) -> Result<Fd, Errno> {
+    let fd = Errno::result(unsafe {
+        syscall4(SysNr::OPENAT, AT_FDCWD, path.as_ptr() as isize, flags as isize, mode as isize)
+    })?;
+    Ok(Fd(fd as i32))
+}
+
+/// Close a file descriptor.
+///
+/// # Safety
+///
+/// The descriptor must be owned by the caller, others may still use it otherwise.
+pub unsafe fn close(fd: Fd) -> Result<(), Errno> {
+    Errno::result(unsafe { syscall1(SysNr::CLOSE, fd.0 as isize) })?;
+    Ok(())
+}
+
+/// Create a pipe, returning the read and the write end.
+pub fn pipe(flags: i32) -> Result<(Fd, Fd), Errno> {
+    let mut fds = [0i32; 2];
+    Errno::result(unsafe {
+        syscall2(SysNr::PIPE2, fds.as_mut_ptr() as isize, flags as isize)
+    })?;
+    Ok((Fd(fds[0]), Fd(fds[1])))
+}
+
+pub fn getpid() -> i32 {
+    // Can not fail.
+    unsafe { crate::syscall0(SysNr::GETPID) as i32 }
+}
+
+/// Terminate the calling thread.
+///
+/// All threads of the process are terminated by `exit_group`.
+pub fn exit(code: i32) -> ! {
+    unsafe {
+        syscall1(SysNr::EXIT, code as isize);
+        core::hint::unreachable_unchecked()
+    }
+}
+
+/// Terminate all threads of the process.
+pub fn exit_group(code: i32) -> ! {
+    unsafe {
+        syscall1(SysNr::EXIT_GROUP, code as isize);
+        core::hint::unreachable_unchecked()
+    }
+}
+
+/// Map memory or a file.
+///
+/// # Safety
+///
+/// With `MAP_FIXED` this replaces any existing mapping, which must not be in use.
+pub unsafe fn mmap(
+    addr: *mut u8,
+    len: usize,
+    prot: i32,
+    flags: i32,
+    fd: Option<Fd>,
+    offset: usize,
+) -> Result<*mut u8, Errno> {
+    let fd = fd.map_or(-1, |fd| fd.0);
+    // The `mmap` of x86 takes its arguments in memory, `mmap2` takes the offset in units. An
+    // offset that is not a multiple of them is invalid for `mmap` as well.
+    #[cfg(target_arch = "x86")]
+    let (nr, offset) = if offset % MMAP2_OFFSET_UNIT == 0 {
+        (SysNr::MMAP2, offset / MMAP2_OFFSET_UNIT)
+    } else {
+        return Err(Errno::EINVAL);
+    };
+    #[cfg(not(target_arch = "x86"))]
+    let nr = SysNr::MMAP;
+    let addr = Errno::result(unsafe {
+        syscall6(nr, addr as isize, len as isize, prot as isize, flags as isize, fd as isize, offset as isize)
+    })?;
+    Ok(addr as *mut u8)
+}
+
+/// Remove a mapping.
+///
+/// # Safety
+///
+/// The memory must not be in use.
+pub unsafe fn munmap(addr: *mut u8, len: usize) -> Result<(), Errno> {
+    Errno::result(unsafe { syscall2(SysNr::MUNMAP, addr as isize, len as isize) })?;
+    Ok(())
+}
+
+/// Start a thread running `func(arg)` on `stack`, returning its id.
+///
+/// The thread exits with the return value of `func`. Without `CLONE_VM` this starts a process,
+/// the exit code is then reported to the parent. `stack` is the end of the memory of the new
+/// stack, as it grows downwards, and the thread uses the thread pointer `tls` if `CLONE_SETTLS` is
+/// given or the one of the caller otherwise.
+///
+/// # Safety
+///
+/// The stack must be valid for writes and not be used by anything else until the thread exited.
+/// The function must not rely on thread local storage of the runtime, which is not set up.
+#[cfg(target_arch = "x86_64")]
+pub unsafe fn clone_thread(
+    flags: i32,
+    stack: *mut u8,
+    tls: usize,
+    func: extern "C" fn(usize) -> i32,
+    arg: usize,
+) -> Result<i32, Errno> {
+    // The child returns into `func` with a 16-byte aligned stack, and from it into the exit stub.
+    let top = (stack as usize & !15) as *mut usize;
+    let sp = unsafe { top.sub(4) };
+    unsafe {
+        sp.write(func as *const () as usize);
+        sp.add(1).write(crate::impl_::thread_exit as *const () as usize);
+        sp.add(2).write(SysNr::EXIT.0 as usize);
+    }
+
+    let tid = Errno::result(unsafe {
+        crate::impl_::clone(flags as isize, sp, tls as isize, arg as isize, SysNr::CLONE)
+    })?;
+    Ok(tid as i32)
+}
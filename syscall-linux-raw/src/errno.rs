@@ -0,0 +1,94 @@
+use core::fmt;
+
+/// An error number, as returned by a failed system call.
+///
+/// The kernel returns `-errno` in the range `-4095..=-1`, any other value is a successful result.
+/// The numbers are those of the generic table, shared by x86, arm64 and riscv.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Errno(pub i32);
+
+macro_rules! errno_table {
+    ($($name:ident = $nr:literal, $description:literal;)*) => {
+        impl Errno {
+            $(
+                #[doc = $description]
+                pub const $name: Errno = Errno($nr);
+            )*
+
+            /// The symbolic name and a description, if the number is known.
+            pub fn describe(self) -> Option<(&'static str, &'static str)> {
+                match self.0 {
+                    $($nr => Some((stringify!($name), $description)),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+errno_table! {
+    EPERM = 1, "Operation not permitted";
+    ENOENT = 2, "No such file or directory";
+    ESRCH = 3, "No such process";
+    EINTR = 4, "Interrupted system call";
+    EIO = 5, "I/O error";
+    ENXIO = 6, "No such device or address";
+    E2BIG = 7, "Argument list too long";
+    ENOEXEC = 8, "Exec format error";
+    EBADF = 9, "Bad file number";
+    ECHILD = 10, "No child processes";
+    EAGAIN = 11, "Try again";
+    ENOMEM = 12, "Out of memory";
+    EACCES = 13, "Permission denied";
+    EFAULT = 14, "Bad address";
+    ENOTBLK = 15, "Block device required";
+    EBUSY = 16, "Device or resource busy";
+    EEXIST = 17, "File exists";
+    EXDEV = 18, "Cross-device link";
+    ENODEV = 19, "No such device";
+    ENOTDIR = 20, "Not a directory";
+    EISDIR = 21, "Is a directory";
+    EINVAL = 22, "Invalid argument";
+    ENFILE = 23, "File table overflow";
+    EMFILE = 24, "Too many open files";
+    ENOTTY = 25, "Not a typewriter";
+    ETXTBSY = 26, "Text file busy";
+    EFBIG = 27, "File too large";
+    ENOSPC = 28, "No space left on device";
+    ESPIPE = 29, "Illegal seek";
+    EROFS = 30, "Read-only file system";
+    EMLINK = 31, "Too many links";
+    EPIPE = 32, "Broken pipe";
+    EDOM = 33, "Math argument out of domain of func";
+    ERANGE = 34, "Math result not representable";
+    EDEADLK = 35, "Resource deadlock would occur";
+    ENAMETOOLONG = 36, "File name too long";
+    ENOLCK = 37, "No record locks available";
+    ENOSYS = 38, "Invalid system call number";
+    ENOTEMPTY = 39, "Directory not empty";
+    ELOOP = 40, "Too many symbolic links encountered";
+    EOVERFLOW = 75, "Value too large for defined data type";
+    EOPNOTSUPP = 95, "Operation not supported on transport endpoint";
+    ETIMEDOUT = 110, "Connection timed out";
+}
+
+impl Errno {
+    /// Interpret the raw return value of a system call.
+    pub fn result(ret: isize) -> Result<usize, Errno> {
+        if (-4095..=-1).contains(&ret) {
+            Err(Errno(-ret as i32))
+        } else {
+            Ok(ret as usize)
+        }
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.describe() {
+            Some((name, description)) => write!(f, "{} ({})", description, name),
+            None => write!(f, "Unknown error {}", self.0),
+        }
+    }
+}
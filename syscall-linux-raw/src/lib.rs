@@ -40,8 +40,6 @@
 #[path = "x86_64.rs"]
 mod impl_;
 
-/* These are not yet supported by the assembler. Compiling them would yield invalid
- * instructions on their platforms.
 #[cfg(target_arch = "x86")]
 #[path = "x86.rs"]
 mod impl_;
@@ -49,7 +47,20 @@ mod impl_;
 #[cfg(target_arch = "aarch64")]
 #[path = "aarch64.rs"]
 mod impl_;
-*/
+
+#[cfg(any(target_os = "linux", target_os = "none"))]
+pub mod calls;
+mod errno;
+#[cfg(all(target_arch = "x86_64", any(target_os = "linux", target_os = "none")))]
+pub mod start;
+#[cfg(all(target_arch = "x86_64", any(target_os = "linux", target_os = "none")))]
+pub mod signal;
+#[cfg(any(target_os = "linux", target_os = "none"))]
+pub mod vdso;
+
+#[cfg(any(target_os = "linux", target_os = "none"))]
+pub use calls::Fd;
+pub use errno::Errno;
 
 
 /// A helper to distinguish the syscall number from other parameters.
@@ -60,9 +71,27 @@ mod impl_;
 ///
 /// This further enables a namespaced access to constants and call numbers that are conditionally
 /// available per system ABI.
+///
+/// The constants of the target are generated from the tables of the kernel, for example
+/// `SysNr::WRITE` or `SysNr::EXIT`.
 #[repr(transparent)]
 pub struct SysNr(pub isize);
 
+#[cfg(all(target_arch = "x86_64", target_pointer_width = "64"))]
+include!(concat!(env!("OUT_DIR"), "/x86_64.rs"));
+
+#[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
+include!(concat!(env!("OUT_DIR"), "/x32.rs"));
+
+#[cfg(target_arch = "x86")]
+include!(concat!(env!("OUT_DIR"), "/i386.rs"));
+
+#[cfg(target_arch = "aarch64")]
+include!(concat!(env!("OUT_DIR"), "/aarch64.rs"));
+
+#[cfg(target_arch = "riscv64")]
+include!(concat!(env!("OUT_DIR"), "/riscv64.rs"));
+
 #[cfg(any(target_os = "linux", target_os = "none"))]
 pub unsafe fn syscall0(nr: SysNr) -> isize {
     // Translate to the inner abi
@@ -98,3 +127,9 @@ pub unsafe fn syscall5(nr: SysNr, a: isize, b: isize, c: isize, d: isize, e: isi
     // Translate to the inner abi
     unsafe { impl_::call5(a, b, c, d, e, nr) }
 }
+
+#[cfg(any(target_os = "linux", target_os = "none"))]
+pub unsafe fn syscall6(nr: SysNr, a: isize, b: isize, c: isize, d: isize, e: isize, f: isize) -> isize {
+    // Translate to the inner abi
+    unsafe { impl_::call6(a, b, c, d, e, f, nr) }
+}
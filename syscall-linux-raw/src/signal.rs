@@ -0,0 +1,159 @@
+//! Signal handlers installed with `rt_sigaction`.
+//!
+//! The kernel structures differ from those of the C library. The signal set is a single word of
+//! 64 signals instead of 1024, and the action has a field for the restorer. That is the code that
+//! a handler returns to, it must call `rt_sigreturn` to restore the interrupted state. The C
+//! library provides it, here it is assembled and set on every action.
+use core::ffi::c_void;
+
+use crate::{syscall2, syscall3, syscall4, Errno, SysNr};
+
+pub const SIGHUP: i32 = 1;
+pub const SIGINT: i32 = 2;
+pub const SIGQUIT: i32 = 3;
+pub const SIGILL: i32 = 4;
+pub const SIGTRAP: i32 = 5;
+pub const SIGABRT: i32 = 6;
+pub const SIGBUS: i32 = 7;
+pub const SIGFPE: i32 = 8;
+pub const SIGKILL: i32 = 9;
+pub const SIGUSR1: i32 = 10;
+pub const SIGSEGV: i32 = 11;
+pub const SIGUSR2: i32 = 12;
+pub const SIGPIPE: i32 = 13;
+pub const SIGALRM: i32 = 14;
+pub const SIGTERM: i32 = 15;
+pub const SIGCHLD: i32 = 17;
+
+/// Call the handler with the signal information and the interrupted context.
+pub const SA_SIGINFO: u64 = 0x4;
+pub const SA_RESTORER: u64 = 0x0400_0000;
+pub const SA_ONSTACK: u64 = 0x0800_0000;
+pub const SA_RESTART: u64 = 0x1000_0000;
+pub const SA_NODEFER: u64 = 0x4000_0000;
+pub const SA_RESETHAND: u64 = 0x8000_0000;
+
+/// The default action of the signal, in place of a handler.
+pub const SIG_DFL: usize = 0;
+/// Ignore the signal, in place of a handler.
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: i32 = 0;
+pub const SIG_UNBLOCK: i32 = 1;
+pub const SIG_SETMASK: i32 = 2;
+
+/// A set of signals, `sigset_t` of the kernel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct SigSet(pub u64);
+
+/// The action on a signal, `struct sigaction` of the kernel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct SigAction {
+    /// The handler, `SIG_DFL` or `SIG_IGN`.
+    pub handler: usize,
+    pub flags: u64,
+    pub restorer: usize,
+    /// Signals blocked while the handler runs, in addition to the signal itself.
+    pub mask: SigSet,
+}
+
+impl SigSet {
+    pub const fn empty() -> Self {
+        SigSet(0)
+    }
+
+    pub const fn with(self, signal: i32) -> Self {
+        SigSet(self.0 | 1 << (signal - 1))
+    }
+
+    pub const fn contains(self, signal: i32) -> bool {
+        self.0 & 1 << (signal - 1) != 0
+    }
+}
+
+impl SigAction {
+    /// Call `handler` with the number of the signal.
+    pub fn new(handler: extern "C" fn(i32)) -> Self {
+        Self::with_handler(handler as *const () as usize, 0)
+    }
+
+    /// Call `handler` with the number, the `siginfo_t` and the `ucontext_t` of the signal.
+    pub fn siginfo(handler: extern "C" fn(i32, *mut c_void, *mut c_void)) -> Self {
+        Self::with_handler(handler as *const () as usize, SA_SIGINFO)
+    }
+
+    /// The action for `SIG_DFL`, `SIG_IGN` or the address of a handler.
+    pub fn with_handler(handler: usize, flags: u64) -> Self {
+        SigAction {
+            handler,
+            flags: flags | SA_RESTORER,
+            restorer: crate::impl_::restorer as *const () as usize,
+            mask: SigSet::empty(),
+        }
+    }
+}
+
+/// Change the action on a signal, returning the previous one.
+///
+/// # Safety
+///
+/// The handler runs at any point of the program, interrupting it. It must only call functions
+/// that are safe to be reentered, and any restorer must call `rt_sigreturn`.
+pub unsafe fn rt_sigaction(signal: i32, action: Option<&SigAction>) -> Result<SigAction, Errno> {
+    let mut old = SigAction::default();
+    let action = action.map_or(core::ptr::null(), |action| action as *const SigAction);
+    Errno::result(unsafe {
+        syscall4(
+            SysNr::RT_SIGACTION,
+            signal as isize,
+            action as isize,
+            &mut old as *mut _ as isize,
+            core::mem::size_of::<SigSet>() as isize,
+        )
+    })?;
+    Ok(old)
+}
+
+/// Change the signals blocked by the calling thread, returning the previous set.
+pub fn rt_sigprocmask(how: i32, set: Option<&SigSet>) -> Result<SigSet, Errno> {
+    let mut old = SigSet::empty();
+    let set = set.map_or(core::ptr::null(), |set| set as *const SigSet);
+    Errno::result(unsafe {
+        syscall4(
+            SysNr::RT_SIGPROCMASK,
+            how as isize,
+            set as isize,
+            &mut old as *mut _ as isize,
+            core::mem::size_of::<SigSet>() as isize,
+        )
+    })?;
+    Ok(old)
+}
+
+/// The signals that are blocked and were raised for the calling thread or the process.
+pub fn rt_sigpending() -> Result<SigSet, Errno> {
+    let mut pending = SigSet::empty();
+    Errno::result(unsafe {
+        syscall2(
+            SysNr::RT_SIGPENDING,
+            &mut pending as *mut _ as isize,
+            core::mem::size_of::<SigSet>() as isize,
+        )
+    })?;
+    Ok(pending)
+}
+
+/// Send a signal to the calling thread.
+pub fn raise(signal: i32) -> Result<(), Errno> {
+    let (pid, tid) = unsafe { (crate::syscall0(SysNr::GETPID), crate::syscall0(SysNr::GETTID)) };
+    Errno::result(unsafe { syscall3(SysNr::TGKILL, pid, tid, signal as isize) })?;
+    Ok(())
+}
+
+/// Send a signal to a process.
+pub fn kill(pid: i32, signal: i32) -> Result<(), Errno> {
+    Errno::result(unsafe { syscall2(SysNr::KILL, pid as isize, signal as isize) })?;
+    Ok(())
+}
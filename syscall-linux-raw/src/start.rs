@@ -0,0 +1,188 @@
+//! Program entry without a C runtime.
+//!
+//! The kernel starts a program at `_start`, with the stack pointer at the argument count. It is
+//! followed by the arguments, the environment and the auxiliary vector:
+//!
+//! ```text
+//! [rsp]       argc
+//! [rsp+8]     argv[0], .., argv[argc-1], null
+//!             envp[0], .., null
+//!             auxv pairs of key and value, until AT_NULL
+//! ```
+//!
+//! A Rust function can not define `_start` as it would already have moved the stack pointer. The
+//! `entry!` macro instead defines it with `global_asm!`, as a trampoline passing the initial stack
+//! pointer on to `start`. It can not be written with `#[assemble]`: the code is assembled before
+//! the crate is compiled, its bytes can not hold the relocation for calling a Rust function.
+use core::ffi::{c_char, CStr};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::calls;
+
+/// The auxiliary vector, if the program was started by `entry!`.
+static AUXV: AtomicUsize = AtomicUsize::new(0);
+
+/// Define `_start` to call `main` with the arguments, environment and auxiliary vector.
+///
+/// The process exits with the return value of `main`.
+///
+/// ```ignore
+/// fn main(args: Args, env: Env, auxv: Auxv) -> i32 {
+///     args.len() as i32
+/// }
+///
+/// syscall_linux_raw::entry!(main);
+/// ```
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[doc(hidden)]
+        extern "C" fn __syscall_linux_raw_start(stack: *const usize) -> ! {
+            unsafe { $crate::start::start(stack, $main) }
+        }
+
+        // Not with `#[assemble]`, only `global_asm!` can call the function by its symbol.
+        ::core::arch::global_asm!(
+            ".pushsection .text._start,\"ax\",%progbits",
+            ".globl _start",
+            ".type _start,%function",
+            "_start:",
+            ".cfi_startproc",
+            // The outermost frame, there is no return address to unwind to.
+            ".cfi_undefined rip",
+            "xor ebp, ebp",
+            "mov rdi, rsp",
+            "and rsp, -16",
+            "call {start}",
+            "ud2",
+            ".cfi_endproc",
+            ".size _start, . - _start",
+            ".popsection",
+            start = sym __syscall_linux_raw_start,
+        );
+    };
+}
+
+/// Call `main` with the initial stack and exit with its return value.
+///
+/// # Safety
+///
+/// `stack` must be the stack pointer on entry of the process.
+#[doc(hidden)]
+pub unsafe fn start(stack: *const usize, main: fn(Args, Env, Auxv) -> i32) -> ! {
+    let (args, env, auxv) = unsafe {
+        let argc = *stack;
+        let argv = stack.add(1) as *const *const c_char;
+        let envp = argv.add(argc + 1);
+
+        let mut end = envp;
+        while !(*end).is_null() {
+            end = end.add(1);
+        }
+
+        (Args { argc, argv }, Env { envp }, Auxv { auxv: end.add(1) as *const [usize; 2] })
+    };
+
+    AUXV.store(auxv.auxv as usize, Ordering::Relaxed);
+    calls::exit_group(main(args, env, auxv))
+}
+
+/// The auxiliary vector, if the program was started by `entry!`.
+pub fn auxv() -> Option<Auxv> {
+    match AUXV.load(Ordering::Relaxed) {
+        0 => None,
+        auxv => Some(Auxv { auxv: auxv as *const [usize; 2] }),
+    }
+}
+
+/// The command line arguments.
+#[derive(Clone, Copy)]
+pub struct Args {
+    argc: usize,
+    argv: *const *const c_char,
+}
+
+/// The environment, as `KEY=value` strings.
+#[derive(Clone, Copy)]
+pub struct Env {
+    envp: *const *const c_char,
+}
+
+/// The auxiliary vector, information about the process from the kernel.
+#[derive(Clone, Copy)]
+pub struct Auxv {
+    auxv: *const [usize; 2],
+}
+
+impl Args {
+    pub fn len(&self) -> usize {
+        self.argc
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.argc == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&'static CStr> {
+        if idx < self.argc {
+            Some(unsafe { CStr::from_ptr(*self.argv.add(idx)) })
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&'static CStr> {
+        let args = *self;
+        (0..args.argc).filter_map(move |idx| args.get(idx))
+    }
+}
+
+impl Env {
+    pub fn iter(&self) -> impl Iterator<Item=&'static CStr> {
+        let mut envp = self.envp;
+        core::iter::from_fn(move || unsafe {
+            if (*envp).is_null() {
+                return None;
+            }
+            let var = CStr::from_ptr(*envp);
+            envp = envp.add(1);
+            Some(var)
+        })
+    }
+
+    /// The value of a variable.
+    pub fn get(&self, key: &str) -> Option<&'static CStr> {
+        self.iter().find_map(|var| {
+            let rest = var.to_bytes_with_nul().strip_prefix(key.as_bytes())?;
+            let value = rest.strip_prefix(b"=")?;
+            CStr::from_bytes_with_nul(value).ok()
+        })
+    }
+}
+
+impl Auxv {
+    pub const NULL: usize = 0;
+    pub const PAGESZ: usize = 6;
+    pub const ENTRY: usize = 9;
+    pub const RANDOM: usize = 25;
+    pub const EXECFN: usize = 31;
+    pub const SYSINFO_EHDR: usize = 33;
+
+    /// Pairs of key and value.
+    pub fn iter(&self) -> impl Iterator<Item=(usize, usize)> {
+        let mut auxv = self.auxv;
+        core::iter::from_fn(move || unsafe {
+            let [key, value] = *auxv;
+            if key == Self::NULL {
+                return None;
+            }
+            auxv = auxv.add(1);
+            Some((key, value))
+        })
+    }
+
+    pub fn get(&self, key: usize) -> Option<usize> {
+        self.iter().find(|&(entry, _)| entry == key).map(|(_, value)| value)
+    }
+}
+
@@ -0,0 +1,261 @@
+//! Calls through the virtual dynamic shared object of the kernel.
+//!
+//! The vDSO is an ELF image mapped into every process, its address is the `AT_SYSINFO_EHDR`
+//! entry of the auxiliary vector. Its functions answer calls such as `clock_gettime` without
+//! entering the kernel. We take the auxiliary vector from the initial stack when the program was
+//! started by `entry!`, otherwise read it from `/proc/self/auxv`, and look the symbols up in the
+//! dynamic symbol table, falling back to the system call when either is unavailable. Only a 64-bit
+//! image is read, the calls on x86 always enter the kernel. Its vDSO functions would take the
+//! 32-bit layout of `timespec` and `timeval` as well.
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::calls::{self, Fd};
+use crate::{syscall2, syscall3, Errno, SysNr};
+
+/// A point in time, or a duration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+/// Microsecond resolution time, of `gettimeofday`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Timeval {
+    pub tv_sec: i64,
+    pub tv_usec: i64,
+}
+
+pub const CLOCK_REALTIME: i32 = 0;
+pub const CLOCK_MONOTONIC: i32 = 1;
+
+/// Not yet looked up, any other value is the address or `ABSENT`.
+const UNRESOLVED: usize = 0;
+const ABSENT: usize = 1;
+
+#[cfg(not(target_arch = "aarch64"))]
+mod names {
+    pub const CLOCK_GETTIME: &str = "__vdso_clock_gettime";
+    pub const GETTIMEOFDAY: &str = "__vdso_gettimeofday";
+    pub const GETCPU: Option<&str> = Some("__vdso_getcpu");
+}
+
+/// The vDSO of arm64 names its functions after the kernel, and has no `getcpu`.
+#[cfg(target_arch = "aarch64")]
+mod names {
+    pub const CLOCK_GETTIME: &str = "__kernel_clock_gettime";
+    pub const GETTIMEOFDAY: &str = "__kernel_gettimeofday";
+    pub const GETCPU: Option<&str> = None;
+}
+
+static CLOCK_GETTIME: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+static GETTIMEOFDAY: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+static GETCPU: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+
+pub fn clock_gettime(clock: i32, time: &mut Timespec) -> Result<(), Errno> {
+    let ret = match resolve(&CLOCK_GETTIME, names::CLOCK_GETTIME) {
+        Some(address) => unsafe {
+            let function: extern "C" fn(i32, *mut Timespec) -> i32 = core::mem::transmute(address);
+            function(clock, time) as isize
+        },
+        None => unsafe { syscall2(SYS_CLOCK_GETTIME, clock as isize, time as *mut _ as isize) },
+    };
+    Errno::result(ret).map(drop)
+}
+
+/// The call with a 64-bit `tv_sec`, as in `Timespec`.
+#[cfg(not(target_arch = "x86"))]
+const SYS_CLOCK_GETTIME: SysNr = SysNr::CLOCK_GETTIME;
+#[cfg(target_arch = "x86")]
+const SYS_CLOCK_GETTIME: SysNr = SysNr::CLOCK_GETTIME64;
+
+pub fn gettimeofday(time: &mut Timeval) -> Result<(), Errno> {
+    let ret = match resolve(&GETTIMEOFDAY, names::GETTIMEOFDAY) {
+        Some(address) => unsafe {
+            let function: extern "C" fn(*mut Timeval, usize) -> i32 = core::mem::transmute(address);
+            function(time, 0) as isize
+        },
+        None => gettimeofday_syscall(time),
+    };
+    Errno::result(ret).map(drop)
+}
+
+#[cfg(not(target_arch = "x86"))]
+fn gettimeofday_syscall(time: &mut Timeval) -> isize {
+    unsafe { syscall2(SysNr::GETTIMEOFDAY, time as *mut _ as isize, 0) }
+}
+
+/// The kernel fills in two 32-bit `long` on x86.
+#[cfg(target_arch = "x86")]
+fn gettimeofday_syscall(time: &mut Timeval) -> isize {
+    let mut short = [0i32; 2];
+    let ret = unsafe { syscall2(SysNr::GETTIMEOFDAY, short.as_mut_ptr() as isize, 0) };
+    *time = Timeval { tv_sec: short[0].into(), tv_usec: short[1].into() };
+    ret
+}
+
+/// The CPU and NUMA node that the thread is running on.
+pub fn getcpu() -> Result<(u32, u32), Errno> {
+    let (mut cpu, mut node) = (0u32, 0u32);
+    let ret = match names::GETCPU.and_then(|name| resolve(&GETCPU, name)) {
+        Some(address) => unsafe {
+            let function: extern "C" fn(*mut u32, *mut u32, usize) -> i32 = core::mem::transmute(address);
+            function(&mut cpu, &mut node, 0) as isize
+        },
+        None => unsafe { syscall3(SysNr::GETCPU, &mut cpu as *mut _ as isize, &mut node as *mut _ as isize, 0) },
+    };
+    Errno::result(ret).map(|_| (cpu, node))
+}
+
+/// The address of a function in the vDSO, looked up once.
+fn resolve(cache: &AtomicUsize, name: &str) -> Option<usize> {
+    let address = match cache.load(Ordering::Relaxed) {
+        UNRESOLVED => {
+            let address = symbol(name).unwrap_or(ABSENT);
+            // Racing threads find the same address.
+            cache.store(address, Ordering::Relaxed);
+            address
+        },
+        address => address,
+    };
+
+    if address == ABSENT { None } else { Some(address) }
+}
+
+/// The address of a function symbol in the vDSO, such as `__vdso_clock_gettime`.
+///
+/// Always `None` for the 32-bit vDSO of x86.
+pub fn symbol(name: &str) -> Option<usize> {
+    base().and_then(|base| unsafe { lookup(base, name.as_bytes()) })
+}
+
+/// The address of the vDSO image, from the auxiliary vector.
+pub fn base() -> Option<usize> {
+    const AT_SYSINFO_EHDR: usize = 33;
+    auxv_entry(AT_SYSINFO_EHDR).filter(|&base| base != 0)
+}
+
+/// The value of an entry of the auxiliary vector.
+pub(crate) fn auxv_entry(key: usize) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if let Some(auxv) = crate::start::auxv() {
+            return auxv.get(key);
+        }
+    }
+
+    const AT_NULL: usize = 0;
+
+    let path = b"/proc/self/auxv\0";
+    let path = core::ffi::CStr::from_bytes_with_nul(path).unwrap();
+    let fd = calls::open(path, calls::O_RDONLY | calls::O_CLOEXEC, 0).ok()?;
+    let value = find_entry(fd, key, AT_NULL);
+    let _ = unsafe { calls::close(fd) };
+    value
+}
+
+/// Read pairs of key and value, each a word, until `key` or `end` is found.
+fn find_entry(fd: Fd, key: usize, end: usize) -> Option<usize> {
+    const WORD: usize = core::mem::size_of::<usize>();
+    let mut entry = [0u8; 2 * WORD];
+    loop {
+        let mut filled = 0;
+        while filled < entry.len() {
+            match calls::read(fd, &mut entry[filled..]) {
+                Ok(0) | Err(_) => return None,
+                Ok(len) => filled += len,
+            }
+        }
+
+        let mut word = [0u8; WORD];
+        word.copy_from_slice(&entry[..WORD]);
+        let entry_key = usize::from_ne_bytes(word);
+        word.copy_from_slice(&entry[WORD..]);
+        let value = usize::from_ne_bytes(word);
+
+        match entry_key {
+            k if k == key => return Some(value),
+            k if k == end => return None,
+            _ => {},
+        }
+    }
+}
+
+/// Find a function symbol in the ELF image at `base`.
+///
+/// # Safety
+///
+/// `base` must point to a loaded 64-bit ELF image, such as the vDSO.
+unsafe fn lookup(base: usize, name: &[u8]) -> Option<usize> {
+    const PT_LOAD: u32 = 1;
+    const PT_DYNAMIC: u32 = 2;
+    const DT_NULL: i64 = 0;
+    const DT_HASH: i64 = 4;
+    const DT_STRTAB: i64 = 5;
+    const DT_SYMTAB: i64 = 6;
+    const STT_FUNC: u8 = 2;
+    const STB_GLOBAL: u8 = 1;
+    const STB_WEAK: u8 = 2;
+
+    let read_u16 = |at: usize| unsafe { (at as *const u16).read_unaligned() };
+    let read_u32 = |at: usize| unsafe { (at as *const u32).read_unaligned() };
+    let read_u64 = |at: usize| unsafe { (at as *const u64).read_unaligned() };
+
+    if unsafe { core::slice::from_raw_parts(base as *const u8, 5) } != b"\x7fELF\x02" {
+        return None;
+    }
+
+    // Addresses in the image are relative to the virtual address of the first load segment.
+    let phoff = read_u64(base + 0x20) as usize;
+    let phentsize = read_u16(base + 0x36) as usize;
+    let phnum = read_u16(base + 0x38) as usize;
+    let mut bias = None;
+    let mut dynamic = None;
+    for idx in 0..phnum {
+        let phdr = base + phoff + idx * phentsize;
+        let (p_offset, p_vaddr) = (read_u64(phdr + 0x08) as usize, read_u64(phdr + 0x10) as usize);
+        match read_u32(phdr) {
+            PT_LOAD if bias.is_none() => bias = Some((base + p_offset).wrapping_sub(p_vaddr)),
+            PT_DYNAMIC => dynamic = Some(p_vaddr),
+            _ => {},
+        }
+    }
+    let bias = bias?;
+    let mut entry = bias.wrapping_add(dynamic?);
+
+    let (mut hash, mut strtab, mut symtab) = (None, None, None);
+    loop {
+        let (tag, value) = (read_u64(entry) as i64, read_u64(entry + 8) as usize);
+        match tag {
+            DT_NULL => break,
+            DT_HASH => hash = Some(bias.wrapping_add(value)),
+            DT_STRTAB => strtab = Some(bias.wrapping_add(value)),
+            DT_SYMTAB => symtab = Some(bias.wrapping_add(value)),
+            _ => {},
+        }
+        entry += 16;
+    }
+    let (hash, strtab, symtab) = (hash?, strtab?, symtab?);
+
+    // The number of chain entries is the number of symbols.
+    let count = read_u32(hash + 4) as usize;
+    for idx in 0..count {
+        let sym = symtab + idx * 24;
+        let info = unsafe { *((sym + 4) as *const u8) };
+        let shndx = read_u16(sym + 6);
+        let (binding, kind) = (info >> 4, info & 0xf);
+        if kind != STT_FUNC || shndx == 0 || (binding != STB_GLOBAL && binding != STB_WEAK) {
+            continue;
+        }
+
+        let sym_name = strtab + read_u32(sym) as usize;
+        let sym_name = unsafe { core::ffi::CStr::from_ptr(sym_name as *const _) };
+        if sym_name.to_bytes() == name {
+            return Some(bias.wrapping_add(read_u64(sym + 8) as usize));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,196 @@
+//! System calls of i386, entered through `__kernel_vsyscall` of the vDSO.
+//!
+//! The vDSO chooses the fastest way into the kernel, such as `sysenter`. Its entry is the
+//! `AT_SYSINFO` value of the auxiliary vector, without it we fall back to `int $0x80`. Both take
+//! the arguments in `ebx`, `ecx`, `edx`, `esi`, `edi` and `ebp` and preserve all but `eax`.
+//!
+//! The arguments of the C convention are on the stack, above the return address. We save the
+//! callee-saved registers that are arguments of the call first, the offsets are relative to the
+//! stack pointer after the `push`.
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::SysNr;
+
+const AT_SYSINFO: usize = 32;
+
+/// The address to call to enter the kernel, or zero if not yet looked up.
+static ENTRY: AtomicUsize = AtomicUsize::new(0);
+
+fn entry() -> usize {
+    match ENTRY.load(Ordering::Relaxed) {
+        0 => {
+            // Reading the auxiliary vector makes system calls itself.
+            let int80 = int80 as *const () as usize;
+            ENTRY.store(int80, Ordering::Relaxed);
+            let entry = crate::vdso::auxv_entry(AT_SYSINFO).unwrap_or(int80);
+            ENTRY.store(entry, Ordering::Relaxed);
+            entry
+        },
+        entry => entry,
+    }
+}
+
+pub unsafe fn call0(nr: SysNr) -> isize {
+    unsafe { enter0(nr, entry()) }
+}
+
+pub unsafe fn call1(a: isize, nr: SysNr) -> isize {
+    unsafe { enter1(a, nr, entry()) }
+}
+
+pub unsafe fn call2(a: isize, b: isize, nr: SysNr) -> isize {
+    unsafe { enter2(a, b, nr, entry()) }
+}
+
+pub unsafe fn call3(a: isize, b: isize, c: isize, nr: SysNr) -> isize {
+    unsafe { enter3(a, b, c, nr, entry()) }
+}
+
+pub unsafe fn call4(a: isize, b: isize, c: isize, d: isize, nr: SysNr) -> isize {
+    unsafe { enter4(a, b, c, d, nr, entry()) }
+}
+
+pub unsafe fn call5(a: isize, b: isize, c: isize, d: isize, e: isize, nr: SysNr) -> isize {
+    unsafe { enter5(a, b, c, d, e, nr, entry()) }
+}
+
+pub unsafe fn call6(a: isize, b: isize, c: isize, d: isize, e: isize, f: isize, nr: SysNr) -> isize {
+    unsafe { enter6(a, b, c, d, e, f, nr, entry()) }
+}
+
+/// The fallback entry, called like `__kernel_vsyscall` and not with the C convention.
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn int80() {
+    "int 0x80";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter0(nr: SysNr, entry: usize) -> isize {
+    "mov eax, {nr}";
+    "call {entry}";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter1(_: isize, _: SysNr, _: usize) -> isize {
+    "push ebx";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebx, -8";
+    "mov ebx, dword ptr [esp+8]";
+    "mov eax, dword ptr [esp+12]";
+    "call dword ptr [esp+16]";
+    "pop ebx";
+    ".cfi_adjust_cfa_offset -4";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter2(_: isize, _: isize, _: SysNr, _: usize) -> isize {
+    "push ebx";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebx, -8";
+    "mov ebx, dword ptr [esp+8]";
+    "mov ecx, dword ptr [esp+12]";
+    "mov eax, dword ptr [esp+16]";
+    "call dword ptr [esp+20]";
+    "pop ebx";
+    ".cfi_adjust_cfa_offset -4";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter3(_: isize, _: isize, _: isize, _: SysNr, _: usize) -> isize {
+    "push ebx";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebx, -8";
+    "mov ebx, dword ptr [esp+8]";
+    "mov ecx, dword ptr [esp+12]";
+    "mov edx, dword ptr [esp+16]";
+    "mov eax, dword ptr [esp+20]";
+    "call dword ptr [esp+24]";
+    "pop ebx";
+    ".cfi_adjust_cfa_offset -4";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter4(_: isize, _: isize, _: isize, _: isize, _: SysNr, _: usize) -> isize {
+    "push ebx";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebx, -8";
+    "push esi";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %esi, -12";
+    "mov ebx, dword ptr [esp+12]";
+    "mov ecx, dword ptr [esp+16]";
+    "mov edx, dword ptr [esp+20]";
+    "mov esi, dword ptr [esp+24]";
+    "mov eax, dword ptr [esp+28]";
+    "call dword ptr [esp+32]";
+    "pop esi";
+    ".cfi_adjust_cfa_offset -4";
+    "pop ebx";
+    ".cfi_adjust_cfa_offset -4";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter5(_: isize, _: isize, _: isize, _: isize, _: isize, _: SysNr, _: usize) -> isize {
+    "push ebx";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebx, -8";
+    "push esi";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %esi, -12";
+    "push edi";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %edi, -16";
+    "mov ebx, dword ptr [esp+16]";
+    "mov ecx, dword ptr [esp+20]";
+    "mov edx, dword ptr [esp+24]";
+    "mov esi, dword ptr [esp+28]";
+    "mov edi, dword ptr [esp+32]";
+    "mov eax, dword ptr [esp+36]";
+    "call dword ptr [esp+40]";
+    "pop edi";
+    ".cfi_adjust_cfa_offset -4";
+    "pop esi";
+    ".cfi_adjust_cfa_offset -4";
+    "pop ebx";
+    ".cfi_adjust_cfa_offset -4";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", arch = "x86")]
+unsafe extern "C" fn enter6(_: isize, _: isize, _: isize, _: isize, _: isize, _: isize, _: SysNr, _: usize) -> isize {
+    "push ebx";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebx, -8";
+    "push esi";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %esi, -12";
+    "push edi";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %edi, -16";
+    "push ebp";
+    ".cfi_adjust_cfa_offset 4";
+    ".cfi_offset %ebp, -20";
+    "mov ebx, dword ptr [esp+20]";
+    "mov ecx, dword ptr [esp+24]";
+    "mov edx, dword ptr [esp+28]";
+    "mov esi, dword ptr [esp+32]";
+    "mov edi, dword ptr [esp+36]";
+    "mov ebp, dword ptr [esp+40]";
+    "mov eax, dword ptr [esp+44]";
+    "call dword ptr [esp+48]";
+    "pop ebp";
+    ".cfi_adjust_cfa_offset -4";
+    "pop edi";
+    ".cfi_adjust_cfa_offset -4";
+    "pop esi";
+    ".cfi_adjust_cfa_offset -4";
+    "pop ebx";
+    ".cfi_adjust_cfa_offset -4";
+    "ret";
+}
@@ -1,35 +1,35 @@
 use super::SysNr;
 
 #[direct_asm::assemble]
-pub unsafe extern "C" fn call0(NR: SysNr) -> isize {
+pub unsafe extern "C" fn call0(nr: SysNr) -> isize {
     "mov %rax,%rdi";
     "syscall";
     "ret";
 }
 
 #[direct_asm::assemble]
-pub unsafe extern "C" fn call1(a: isize, NR: SysNr) -> isize {
+pub unsafe extern "C" fn call1(a: isize, nr: SysNr) -> isize {
     "mov %rax,%rsi";
     "syscall";
     "ret";
 }
 
 #[direct_asm::assemble]
-pub unsafe extern "C" fn call2(_: isize, _: isize, NR: SysNr) -> isize {
+pub unsafe extern "C" fn call2(_: isize, _: isize, nr: SysNr) -> isize {
     "mov %rax,%rdx";
     "syscall";
     "ret";
 }
 
 #[direct_asm::assemble]
-pub unsafe extern "C" fn call3(_: isize, _: isize, _: isize, NR: SysNr) -> isize {
+pub unsafe extern "C" fn call3(_: isize, _: isize, _: isize, nr: SysNr) -> isize {
     "mov %rax,%rcx";
     "syscall";
     "ret";
 }
 
 #[direct_asm::assemble]
-pub unsafe extern "C" fn call4(_: isize, _: isize, _: isize, _: isize, NR: SysNr) -> isize {
+pub unsafe extern "C" fn call4(_: isize, _: isize, _: isize, _: isize, nr: SysNr) -> isize {
     // syscall clobbers %rcx and %r11
     // See https://stackoverflow.com/questions/47983371/why-do-x86-64-linux-system-calls-modify-rcx-and-what-does-the-value-mean/47997378#47997378
     "mov %r10,%rcx";
@@ -39,11 +39,57 @@ pub unsafe extern "C" fn call4(_: isize, _: isize, _: isize, _: isize, NR: SysNr
 }
 
 #[direct_asm::assemble]
-pub unsafe extern "C" fn call5(_: isize, _: isize, _: isize, _: isize, _: isize, NR: SysNr) -> isize {
+pub unsafe extern "C" fn call5(_: isize, _: isize, _: isize, _: isize, _: isize, nr: SysNr) -> isize {
     "mov %r10,%rcx";
     "mov %rax,%r9";
     "syscall";
     "ret";
 }
 
-// TODO: figure out call6
+#[direct_asm::assemble]
+pub unsafe extern "C" fn call6(_: isize, _: isize, _: isize, _: isize, _: isize, _: isize, nr: SysNr) -> isize {
+    // The number is the seventh argument, on the stack above the return address. Take both off
+    // and restore them, the caller owns that stack slot. `syscall` clobbers %r11 after this.
+    "pop %r11";
+    "pop %rax";
+    "push %rax";
+    "push %r11";
+    "mov %r10,%rcx";
+    "syscall";
+    "ret";
+}
+
+#[direct_asm::assemble]
+pub unsafe extern "C" fn clone(flags: isize, stack: *mut usize, tls: isize, arg: isize, nr: SysNr) -> isize {
+    // Both the parent and the child continue after `syscall`, there is no branch on the result.
+    // The parent returns the thread id to the caller. The child starts on `stack`, prepared with
+    // the thread function as the return address, and returns into it with the argument.
+    "mov %rax,%r8";
+    "mov %r8,%rdx";
+    "mov %r9,%rcx";
+    "xor %rdx,%rdx";
+    "xor %r10,%r10";
+    "syscall";
+    "mov %rdi,%r9";
+    "ret";
+}
+
+#[direct_asm::assemble]
+pub unsafe extern "C" fn thread_exit() -> ! {
+    // The return address of the thread function, the call number of `exit` is next on the stack.
+    // The outermost frame of the thread, there is no return address to unwind to.
+    ".cfi_undefined 16";
+    "mov %rdi,%rax";
+    "pop %rax";
+    "syscall";
+    "ud2";
+}
+
+#[direct_asm::assemble]
+pub unsafe extern "C" fn restorer() -> ! {
+    // The return address of a signal handler, with the signal frame on the stack. There are no
+    // arguments to pass the call number, this is `rt_sigreturn` of x86_64.
+    "mov %rax,15";
+    "syscall";
+    "ud2";
+}
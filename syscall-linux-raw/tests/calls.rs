@@ -0,0 +1,64 @@
+use syscall_linux_raw::calls::{self, Fd};
+use syscall_linux_raw::Errno;
+
+use core::ffi::CStr;
+
+fn cstr(bytes: &[u8]) -> &CStr {
+    CStr::from_bytes_with_nul(bytes).unwrap()
+}
+
+#[test]
+fn pipe_roundtrip() {
+    let (reader, writer) = calls::pipe(calls::O_CLOEXEC).unwrap();
+    assert_eq!(calls::write(writer, b"Hello"), Ok(5));
+
+    let mut buf = [0; 8];
+    assert_eq!(calls::read(reader, &mut buf), Ok(5));
+    assert_eq!(&buf[..5], b"Hello");
+
+    unsafe {
+        calls::close(reader).unwrap();
+        calls::close(writer).unwrap();
+    }
+}
+
+#[test]
+fn decoded_errors() {
+    let missing = calls::open(cstr(b"/nonexistent/direct-asm\0"), calls::O_RDONLY, 0);
+    assert_eq!(missing, Err(Errno::ENOENT));
+    assert_eq!(calls::write(Fd(-1), b""), Err(Errno::EBADF));
+}
+
+#[test]
+fn open_and_read() {
+    let fd = calls::open(cstr(b"/proc/self/stat\0"), calls::O_RDONLY | calls::O_CLOEXEC, 0).unwrap();
+    let mut buf = [0; 32];
+    let len = calls::read(fd, &mut buf).unwrap();
+    let pid = calls::getpid().to_string();
+    assert!(buf[..len].starts_with(pid.as_bytes()));
+    unsafe { calls::close(fd).unwrap() };
+}
+
+#[test]
+fn map_memory() {
+    const LEN: usize = 4096;
+    let flags = calls::MAP_PRIVATE | calls::MAP_ANONYMOUS;
+    let prot = calls::PROT_READ | calls::PROT_WRITE;
+    unsafe {
+        let addr = calls::mmap(core::ptr::null_mut(), LEN, prot, flags, None, 0).unwrap();
+        addr.add(LEN - 1).write(42);
+        assert_eq!(addr.add(LEN - 1).read(), 42);
+        calls::munmap(addr, LEN).unwrap();
+
+        let unaligned = calls::mmap(core::ptr::null_mut(), LEN, prot, flags, None, 1);
+        assert_eq!(unaligned, Err(Errno::EINVAL));
+    }
+}
+
+#[test]
+fn display() {
+    assert_eq!(Errno::ENOENT.to_string(), "No such file or directory (ENOENT)");
+    assert_eq!(Errno(4000).to_string(), "Unknown error 4000");
+    assert_eq!(Errno::result(-9), Err(Errno::EBADF));
+    assert_eq!(Errno::result(-4096), Ok(-4096isize as usize));
+}
@@ -0,0 +1,56 @@
+// Threads and signals are only implemented for x86_64.
+#![cfg(target_arch = "x86_64")]
+
+use syscall_linux_raw::calls;
+use syscall_linux_raw::{syscall4, SysNr};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const STACK: usize = 64 * 1024;
+const SIGCHLD: i32 = 17;
+
+fn stack() -> *mut u8 {
+    let prot = calls::PROT_READ | calls::PROT_WRITE;
+    let flags = calls::MAP_PRIVATE | calls::MAP_ANONYMOUS;
+    let memory = unsafe { calls::mmap(core::ptr::null_mut(), STACK, prot, flags, None, 0) };
+    // The end, the stack grows downwards.
+    unsafe { memory.unwrap().add(STACK) }
+}
+
+extern "C" fn store(arg: usize) -> i32 {
+    let counter = unsafe { &*(arg as *const AtomicUsize) };
+    counter.store(42, Ordering::Release);
+    0
+}
+
+extern "C" fn exit_code(arg: usize) -> i32 {
+    arg as i32
+}
+
+#[test]
+fn thread_shares_memory() {
+    // Leaked, as well as the stack, since nothing tells us when the thread exited.
+    let counter: &'static AtomicUsize = Box::leak(Box::new(AtomicUsize::new(0)));
+    let arg = counter as *const AtomicUsize as usize;
+
+    let tid = unsafe { calls::clone_thread(calls::CLONE_THREAD_FLAGS, stack(), 0, store, arg) };
+    assert!(tid.unwrap() > 0);
+
+    while counter.load(Ordering::Acquire) == 0 {
+        std::thread::yield_now();
+    }
+    assert_eq!(counter.load(Ordering::Acquire), 42);
+}
+
+#[test]
+fn process_exits_with_result() {
+    // Without shared memory the child is a process, it returns the argument as its exit code.
+    let pid = unsafe { calls::clone_thread(SIGCHLD, stack(), 0, exit_code, 7) }.unwrap();
+
+    let mut status = 0i32;
+    let waited = unsafe { syscall4(SysNr::WAIT4, pid as isize, &mut status as *mut _ as isize, 0, 0) };
+    assert_eq!(waited, pid as isize);
+    // Exited normally, with the code in the second byte.
+    assert_eq!(status & 0x7f, 0);
+    assert_eq!((status >> 8) & 0xff, 7);
+}
@@ -0,0 +1,40 @@
+use syscall_linux_raw::{syscall2, syscall6, SysNr};
+
+const PROT_READ: isize = 0x1;
+const PROT_WRITE: isize = 0x2;
+const MAP_PRIVATE: isize = 0x02;
+const MAP_ANONYMOUS: isize = 0x20;
+
+#[cfg(not(target_arch = "x86"))]
+const MMAP: SysNr = SysNr::MMAP;
+/// The `mmap` of x86 takes its arguments in memory, `mmap2` takes them in registers.
+#[cfg(target_arch = "x86")]
+const MMAP: SysNr = SysNr::MMAP2;
+
+#[test]
+fn map_anonymous() {
+    const LEN: usize = 4096;
+    let addr = unsafe {
+        syscall6(MMAP, 0, LEN as isize, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0)
+    };
+    // Addresses in the upper half are negative on 32-bit targets.
+    assert!(!(-4095..0).contains(&addr), "mmap failed with {}", addr);
+
+    let page = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, LEN) };
+    assert!(page.iter().all(|&byte| byte == 0));
+    page[LEN - 1] = 42;
+    assert_eq!(page[LEN - 1], 42);
+
+    assert_eq!(unsafe { syscall2(SysNr::MUNMAP, addr, LEN as isize) }, 0);
+}
+
+#[test]
+// The offset of `mmap2` is in pages, it can not be unaligned.
+#[cfg(not(target_arch = "x86"))]
+fn error_code() {
+    // An unaligned offset is invalid, the number must have been passed through.
+    let result = unsafe {
+        syscall6(MMAP, 0, 4096, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, -1, 1)
+    };
+    assert_eq!(result, -22);
+}
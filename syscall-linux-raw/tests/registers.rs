@@ -0,0 +1,129 @@
+//! Each `syscallN` must place every argument in its kernel register.
+//!
+//! The calls are chosen such that the result depends on the value of the last argument.
+use syscall_linux_raw::*;
+
+fn close(fd: isize) {
+    assert_eq!(unsafe { syscall1(SysNr::CLOSE, fd) }, 0);
+}
+
+#[test]
+fn arguments0() {
+    let pid = unsafe { syscall0(SysNr::GETPID) };
+    assert_eq!(pid, std::process::id() as isize);
+}
+
+#[test]
+fn arguments1() {
+    // Returns the previous mask, the argument of the first call.
+    let previous = unsafe { syscall1(SysNr::UMASK, 0o027) };
+    assert_eq!(unsafe { syscall1(SysNr::UMASK, previous) }, 0o027);
+}
+
+#[test]
+fn arguments2() {
+    // Returns the new descriptor. Newer architectures only have `dup3`.
+    #[cfg(not(target_arch = "aarch64"))]
+    {
+        let fd = unsafe { syscall2(SysNr::DUP2, 1, 123) };
+        assert_eq!(fd, 123);
+        close(fd);
+    }
+
+    let mut time = [0i64; 2];
+    const CLOCK_MONOTONIC: isize = 1;
+    assert_eq!(unsafe { syscall2(SysNr::CLOCK_GETTIME, CLOCK_MONOTONIC, time.as_mut_ptr() as isize) }, 0);
+    assert!(time != [0, 0]);
+}
+
+#[test]
+fn arguments3() {
+    const O_CLOEXEC: isize = 0o2000000;
+    const F_GETFD: isize = 1;
+    const FD_CLOEXEC: isize = 1;
+
+    let fd = unsafe { syscall3(SysNr::DUP3, 1, 124, O_CLOEXEC) };
+    assert_eq!(fd, 124);
+    assert_eq!(unsafe { syscall3(SysNr::FCNTL, fd, F_GETFD, 0) }, FD_CLOEXEC);
+    close(fd);
+
+    let mut buf = [0u8; 64];
+    let len = unsafe { syscall3(SysNr::GETRANDOM, buf.as_mut_ptr() as isize, 48, 0) };
+    assert_eq!(len, 48);
+    assert!(buf[48..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn arguments4() {
+    const SIG_BLOCK: isize = 0;
+    const SIG_SETMASK: isize = 2;
+    const SIGUSR1: u64 = 10;
+
+    let set: u64 = 1 << (SIGUSR1 - 1);
+    let mut old: u64 = 0;
+    let mut current: u64 = 0;
+    unsafe {
+        // The size of the set is the fourth argument, in `r10`, the kernel rejects any other.
+        assert_eq!(syscall4(SysNr::RT_SIGPROCMASK, SIG_BLOCK, &set as *const _ as isize, 0, 7), -22);
+        assert_eq!(syscall4(SysNr::RT_SIGPROCMASK, SIG_BLOCK, &set as *const _ as isize, &mut old as *mut _ as isize, 8), 0);
+        assert_eq!(syscall4(SysNr::RT_SIGPROCMASK, SIG_SETMASK, &old as *const _ as isize, &mut current as *mut _ as isize, 8), 0);
+    }
+    assert_eq!(current & set, set);
+}
+
+#[test]
+fn arguments5() {
+    const PROT_READ: isize = 0x1;
+    const PROT_WRITE: isize = 0x2;
+    const MAP_PRIVATE: isize = 0x02;
+    const MAP_ANONYMOUS: isize = 0x20;
+    const MREMAP_MAYMOVE: isize = 1;
+    const MREMAP_FIXED: isize = 2;
+    const LEN: isize = 4096;
+    // The `mmap` of x86 takes its arguments in memory, `mmap2` takes them in registers.
+    #[cfg(not(target_arch = "x86"))]
+    const MMAP: SysNr = SysNr::MMAP;
+    #[cfg(target_arch = "x86")]
+    const MMAP: SysNr = SysNr::MMAP2;
+
+    unsafe {
+        let map = |len| syscall6(MMAP, 0, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+        let old = map(LEN);
+        // Reserve a target, then move onto its second page.
+        let reserved = map(3 * LEN);
+        // Addresses in the upper half are negative on 32-bit targets.
+        assert!(!(-4095..0).contains(&old) && !(-4095..0).contains(&reserved));
+        *(old as *mut u8) = 42;
+
+        let target = reserved + LEN;
+        let moved = syscall5(SysNr::MREMAP, old, LEN, LEN, MREMAP_MAYMOVE | MREMAP_FIXED, target);
+        assert_eq!(moved, target);
+        assert_eq!(*(moved as *const u8), 42);
+
+        assert_eq!(syscall2(SysNr::MUNMAP, reserved, 3 * LEN), 0);
+    }
+}
+
+#[test]
+fn arguments6() {
+    #[repr(C)]
+    struct IoVec {
+        base: *mut u8,
+        len: usize,
+    }
+
+    let mut source = *b"echo";
+    let mut target = [0u8; 4];
+    let local = IoVec { base: target.as_mut_ptr(), len: 4 };
+    let remote = IoVec { base: source.as_mut_ptr(), len: 4 };
+    let pid = std::process::id() as isize;
+
+    let read = |flags| unsafe {
+        syscall6(SysNr::PROCESS_VM_READV, pid, &local as *const _ as isize, 1, &remote as *const _ as isize, 1, flags)
+    };
+
+    // The flags are the sixth argument, in `r9`, and must be zero.
+    assert_eq!(read(1), -22);
+    assert_eq!(read(0), 4);
+    assert_eq!(&target, b"echo");
+}
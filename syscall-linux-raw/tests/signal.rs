@@ -0,0 +1,48 @@
+// Threads and signals are only implemented for x86_64.
+#![cfg(target_arch = "x86_64")]
+
+use syscall_linux_raw::signal::{self, SigAction, SigSet};
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn record(signal: i32) {
+    RECEIVED.store(signal, Ordering::SeqCst);
+}
+
+#[test]
+fn handler_returns_through_restorer() {
+    let action = SigAction::new(record);
+    unsafe { signal::rt_sigaction(signal::SIGUSR1, Some(&action)) }.unwrap();
+
+    // Continuing here after the handler requires a working `rt_sigreturn`.
+    signal::raise(signal::SIGUSR1).unwrap();
+    assert_eq!(RECEIVED.load(Ordering::SeqCst), signal::SIGUSR1);
+
+    let previous = unsafe { signal::rt_sigaction(signal::SIGUSR1, None) }.unwrap();
+    assert_eq!(previous, action);
+}
+
+#[test]
+fn blocked_signal_is_pending() {
+    let ignore = SigAction::with_handler(signal::SIG_IGN, 0);
+    let previous = unsafe { signal::rt_sigaction(signal::SIGUSR2, Some(&ignore)) }.unwrap();
+    assert_eq!(previous.handler, signal::SIG_DFL);
+
+    let set = SigSet::empty().with(signal::SIGUSR2);
+    assert!(set.contains(signal::SIGUSR2) && !set.contains(signal::SIGUSR1));
+    let mask = signal::rt_sigprocmask(signal::SIG_BLOCK, Some(&set)).unwrap();
+    assert!(!signal::rt_sigpending().unwrap().contains(signal::SIGUSR2));
+
+    // A blocked signal is kept until unblocked, even when it is ignored.
+    signal::raise(signal::SIGUSR2).unwrap();
+    assert!(signal::rt_sigpending().unwrap().contains(signal::SIGUSR2));
+
+    // Unblocking delivers the signal while it is still ignored.
+    let blocked = signal::rt_sigprocmask(signal::SIG_SETMASK, Some(&mask)).unwrap();
+    assert!(blocked.contains(signal::SIGUSR2));
+    assert!(!signal::rt_sigpending().unwrap().contains(signal::SIGUSR2));
+
+    unsafe { signal::rt_sigaction(signal::SIGUSR2, Some(&previous)) }.unwrap();
+}
@@ -0,0 +1,59 @@
+use syscall_linux_raw::vdso::{self, Timespec, Timeval};
+use syscall_linux_raw::{syscall2, SysNr};
+
+#[cfg(not(target_arch = "aarch64"))]
+const CLOCK_GETTIME: &str = "__vdso_clock_gettime";
+#[cfg(target_arch = "aarch64")]
+const CLOCK_GETTIME: &str = "__kernel_clock_gettime";
+
+/// The call with a 64-bit `tv_sec`, as in `Timespec`.
+#[cfg(not(target_arch = "x86"))]
+const SYS_CLOCK_GETTIME: SysNr = SysNr::CLOCK_GETTIME;
+#[cfg(target_arch = "x86")]
+const SYS_CLOCK_GETTIME: SysNr = SysNr::CLOCK_GETTIME64;
+
+#[test]
+fn located() {
+    assert!(vdso::base().is_some());
+    // Only a 64-bit image is read, x86 always makes the system calls.
+    assert_eq!(vdso::symbol(CLOCK_GETTIME).is_some(), cfg!(target_pointer_width = "64"));
+    assert!(vdso::symbol("__vdso_nonexistent").is_none());
+}
+
+#[test]
+fn monotonic() {
+    let mut before = Timespec::default();
+    let mut syscall = Timespec::default();
+    let mut after = Timespec::default();
+
+    vdso::clock_gettime(vdso::CLOCK_MONOTONIC, &mut before).unwrap();
+    let ret = unsafe { syscall2(SYS_CLOCK_GETTIME, vdso::CLOCK_MONOTONIC as isize, &mut syscall as *mut _ as isize) };
+    assert_eq!(ret, 0);
+    vdso::clock_gettime(vdso::CLOCK_MONOTONIC, &mut after).unwrap();
+
+    let key = |time: Timespec| (time.tv_sec, time.tv_nsec);
+    assert!(key(before) <= key(syscall));
+    assert!(key(syscall) <= key(after));
+}
+
+#[test]
+fn invalid_clock() {
+    let mut time = Timespec::default();
+    assert!(vdso::clock_gettime(-100, &mut time).is_err());
+}
+
+#[test]
+fn wall_clock() {
+    let mut time = Timeval::default();
+    vdso::gettimeofday(&mut time).unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+    assert!((now.as_secs() as i64 - time.tv_sec).abs() <= 1);
+}
+
+#[test]
+fn cpu() {
+    let (cpu, _node) = vdso::getcpu().unwrap();
+    assert!((cpu as usize) < 4096);
+}
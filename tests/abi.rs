@@ -0,0 +1,87 @@
+#[direct_asm::assemble]
+unsafe extern "sysv64" fn add_sysv(a: u64, b: u64) -> u64 {
+    "mov %rax, %rdi";
+    "add %rax, %rsi";
+    "ret";
+}
+
+#[direct_asm::assemble]
+unsafe extern "win64" fn add_win(a: u64, b: u64) -> u64 {
+    "mov %rax, %rcx";
+    "add %rax, %rdx";
+    "ret";
+}
+
+#[test]
+fn explicit_conventions() {
+    assert_eq!(unsafe { add_sysv(40, 2) }, 42);
+    assert_eq!(unsafe { add_win(40, 2) }, 42);
+}
+
+#[direct_asm::assemble(listing, registers_only)]
+unsafe extern "C" fn sixth(_: isize, _: isize, _: isize, _: isize, _: isize, f: isize) -> isize {
+    "mov %rax, %r9";
+    "ret";
+}
+
+#[test]
+fn all_in_registers() {
+    assert_eq!(unsafe { sixth(1, 2, 3, 4, 5, 6) }, 6);
+}
+
+#[direct_asm::assemble]
+unsafe extern "sysv64" fn sub_named(minuend: u64, subtrahend: u64) -> u64 {
+    "mov %rax, {minuend}";
+    "sub %rax, {subtrahend}";
+    "ret";
+}
+
+#[test]
+fn named_arguments() {
+    assert_eq!(unsafe { sub_named(44, 2) }, 42);
+}
+
+#[direct_asm::assemble]
+unsafe extern "sysv64" fn after_array(_: &[u8; 3], n: u64) -> u64 {
+    "mov %rax, {n}";
+    "ret";
+}
+
+#[test]
+fn array_reference_is_one_register() {
+    assert_eq!(unsafe { after_array(b"abc", 42) }, 42);
+}
+
+#[direct_asm::assemble(section = ".text.direct_asm_tests")]
+unsafe extern "C" fn in_section(a: u64) -> u64 {
+    "mov %rax, %rdi";
+    "ret";
+}
+
+#[direct_asm::assemble(section = ".direct_asm_stubs")]
+unsafe extern "C" fn in_stubs(a: u64) -> u64 {
+    "mov %rax, %rdi";
+    "ret";
+}
+
+#[test]
+fn custom_section() {
+    assert_eq!(unsafe { in_section(42) }, 42);
+    assert_eq!(unsafe { in_stubs(42) }, 42);
+}
+
+#[direct_asm::assemble(export_name = "direct_asm_tests_exported")]
+unsafe extern "C" fn exported(a: u64) -> u64 {
+    "mov %rax, %rdi";
+    "ret";
+}
+
+extern "C" {
+    fn direct_asm_tests_exported(a: u64) -> u64;
+}
+
+#[test]
+fn export_name() {
+    assert_eq!(unsafe { exported(42) }, 42);
+    assert_eq!(unsafe { direct_asm_tests_exported(42) }, 42);
+}
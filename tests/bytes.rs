@@ -0,0 +1,19 @@
+const EXIT: [u8; 9] = direct_asm::bytes!("mov %rax, 60"; "syscall");
+
+#[test]
+fn constant() {
+    assert_eq!(EXIT, [0x48, 0xc7, 0xc0, 0x3c, 0, 0, 0, 0x0f, 0x05]);
+}
+
+#[test]
+fn with_backend() {
+    let ret: &[u8] = &direct_asm::bytes!(backend = "gnu-as"; "nop"; "ret");
+    assert_eq!(ret, &[0x90, 0xc3]);
+}
+
+#[test]
+fn with_arch() {
+    // `mov eax, [esp+4]` has no REX prefix in 32-bit code.
+    let load: &[u8] = &direct_asm::bytes!(backend = "gnu-as", arch = "x86"; "mov eax, dword ptr [esp+4]"; "ret");
+    assert_eq!(load, &[0x8b, 0x44, 0x24, 0x04, 0xc3]);
+}
@@ -0,0 +1,21 @@
+use std::backtrace::Backtrace;
+
+#[direct_asm::assemble(frame = 8, save(rbx, r12))]
+unsafe extern "C" fn framed(value: u64, callback: unsafe extern "C" fn(u64) -> u64) -> u64 {
+    "mov %rbx, {value}";
+    "mov %r12, {callback}";
+    "call %r12";
+    "add %rax, %rbx";
+    "ret";
+}
+
+unsafe extern "C" fn double(value: u64) -> u64 {
+    let trace = Backtrace::force_capture().to_string();
+    assert!(trace.contains("calls_within_frame"), "Can not unwind to caller:\n{}", trace);
+    2 * value
+}
+
+#[test]
+fn calls_within_frame() {
+    assert_eq!(unsafe { framed(14, double) }, 42);
+}
@@ -0,0 +1,40 @@
+#[direct_asm::assemble(backend = "gnu-as", export_name = "direct_asm_header_add", c_header = "target/direct-asm-tests.h")]
+pub unsafe extern "C" fn add(a: u64, b: u64) -> u64 {
+    "mov rax, rdi";
+    "add rax, rsi";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", export_name = "direct_asm_header_load", c_header = "target/direct-asm-tests.h")]
+pub unsafe extern "sysv64" fn load(ptr: *const u32, _: usize) -> u32 {
+    "mov eax, dword ptr [rdi]";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", export_name = "direct_asm_header_first", c_header = "target/direct-asm-tests.h")]
+pub unsafe extern "C" fn first(argv: *const *const u8, out: *mut *const u8) -> *const u8 {
+    "mov rax, qword ptr [rdi]";
+    "mov qword ptr [rsi], rax";
+    "ret";
+}
+
+#[test]
+fn declared() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/target/direct-asm-tests.h");
+    let header = std::fs::read_to_string(path).unwrap();
+    assert!(header.contains("#include <stdint.h>"));
+    assert!(header.contains("extern uint64_t direct_asm_header_add(uint64_t a, uint64_t b);"));
+    assert!(header.contains("extern __attribute__((sysv_abi)) uint32_t direct_asm_header_load(const uint32_t *ptr, size_t);"));
+    assert!(header.contains("extern const uint8_t *direct_asm_header_first(const uint8_t *const *argv, const uint8_t **out);"));
+}
+
+#[test]
+fn callable() {
+    assert_eq!(unsafe { add(40, 2) }, 42);
+    assert_eq!(unsafe { load(&42, 0) }, 42);
+
+    let arg = b"arg\0".as_ptr();
+    let mut out = std::ptr::null();
+    assert_eq!(unsafe { first(&arg, &mut out) }, arg);
+    assert_eq!(out, arg);
+}
@@ -0,0 +1,52 @@
+#[direct_asm::assemble(backend = "gnu-as", mode = "inline", safe)]
+extern "sysv64" fn rdtsc() -> u64 {
+    "rdtsc";
+    "shl rdx, 32";
+    "or rax, rdx";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", mode = "inline")]
+unsafe extern "sysv64" fn add(a: u32, b: u64) -> u64 {
+    "mov eax, edi";
+    "add rax, {b}";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", mode = "inline")]
+unsafe extern "win64" fn is_zero(value: u8) -> bool {
+    "xor eax, eax";
+    "test cl, cl";
+    "sete al";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", mode = "inline")]
+unsafe extern "sysv64" fn increment(value: &mut u64) {
+    "add qword ptr [rdi], 1";
+    "ret";
+}
+
+struct Cpu;
+
+impl Cpu {
+    #[direct_asm::assemble(backend = "gnu-as", mode = "inline", safe)]
+    extern "sysv64" fn pause() {
+        "pause";
+    }
+}
+
+#[test]
+fn inlined() {
+    let start = rdtsc();
+    Cpu::pause();
+    assert!(rdtsc() >= start);
+
+    assert_eq!(unsafe { add(40, 2) }, 42);
+    assert!(unsafe { is_zero(0) });
+    assert!(!unsafe { is_zero(1) });
+
+    let mut value = 41;
+    unsafe { increment(&mut value) };
+    assert_eq!(value, 42);
+}
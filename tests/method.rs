@@ -0,0 +1,48 @@
+struct Counter {
+    count: u64,
+}
+
+impl Counter {
+    /// Read the count through the receiver.
+    #[direct_asm::assemble(backend = "gnu-as")]
+    unsafe extern "sysv64" fn get(&self) -> u64 {
+        "mov rax, qword ptr [rdi]";
+        "ret";
+    }
+
+    #[direct_asm::assemble(backend = "gnu-as")]
+    unsafe extern "sysv64" fn add(&mut self, value: u64) {
+        "add qword ptr [{self}], {value}";
+        "ret";
+    }
+
+    #[direct_asm::assemble(backend = "gnu-as", associated)]
+    unsafe extern "sysv64" fn double(value: u64) -> u64 {
+        "lea rax, [rdi + rdi]";
+        "ret";
+    }
+}
+
+trait Identity {
+    #[direct_asm::assemble(backend = "gnu-as", associated)]
+    unsafe extern "sysv64" fn identity(value: u64) -> u64 {
+        "mov rax, rdi";
+        "ret";
+    }
+}
+
+impl Identity for Counter {}
+
+#[test]
+fn methods() {
+    let mut counter = Counter { count: 40 };
+    unsafe { counter.add(2) };
+    assert_eq!(unsafe { counter.get() }, 42);
+    assert_eq!(counter.count, 42);
+}
+
+#[test]
+fn associated() {
+    assert_eq!(unsafe { Counter::double(21) }, 42);
+    assert_eq!(unsafe { Counter::identity(42) }, 42);
+}
@@ -0,0 +1,93 @@
+use std::convert::TryInto;
+
+#[direct_asm::assemble(backend = "gnu-as")]
+unsafe extern "sysv64" fn default_symbol(a: u64) -> u64 {
+    "lea rax, [rdi + 3]";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", export_name = "direct_asm_mode_exported")]
+unsafe extern "sysv64" fn exported_symbol(a: u64) -> u64 {
+    "lea rax, [rdi + 4]";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", mode = "global_asm", export_name = "direct_asm_mode_function")]
+unsafe extern "sysv64" fn function_symbol(a: u64) -> u64 {
+    "lea rax, [rdi + 1]";
+    "ret";
+}
+
+#[direct_asm::assemble(backend = "gnu-as", mode = "static", export_name = "direct_asm_mode_static")]
+unsafe extern "sysv64" fn static_symbol(a: u64) -> u64 {
+    "lea rax, [rdi + 2]";
+    "ret";
+}
+
+#[test]
+fn modes() {
+    assert_eq!(unsafe { default_symbol(39) }, 42);
+    assert_eq!(unsafe { exported_symbol(38) }, 42);
+    assert_eq!(unsafe { function_symbol(41) }, 42);
+    assert_eq!(unsafe { static_symbol(40) }, 42);
+}
+
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn symbol_types() {
+    let binary = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let symbols = elf_symbols(&binary);
+    let named = |name: &str| symbols.iter().find(|symbol| symbol.name == name.as_bytes()).unwrap();
+
+    // Each is `lea` and `ret`, five bytes.
+    let function = named("direct_asm_mode_function");
+    assert_eq!((function.kind, function.size), (STT_FUNC, 5));
+    // The static is padded to its alignment.
+    for name in ["direct_asm_mode_static", "direct_asm_mode_exported"] {
+        let object = named(name);
+        assert_eq!((object.kind, object.size), (STT_OBJECT, 16));
+    }
+
+    // The generated name is not known, find the symbol by its address instead.
+    let bias = function_symbol as *const () as usize - function.value;
+    let address = default_symbol as *const () as usize - bias;
+    let default = symbols.iter().find(|symbol| symbol.value == address && symbol.size > 0).unwrap();
+    assert_eq!((default.kind, default.size), (STT_FUNC, 5));
+}
+
+struct ElfSymbol<'a> {
+    name: &'a [u8],
+    kind: u8,
+    value: usize,
+    size: usize,
+}
+
+/// The symbol table of an ELF64 file.
+fn elf_symbols(elf: &[u8]) -> Vec<ElfSymbol<'_>> {
+    let u16_at = |at: usize| u16::from_le_bytes(elf[at..at + 2].try_into().unwrap()) as usize;
+    let u32_at = |at: usize| u32::from_le_bytes(elf[at..at + 4].try_into().unwrap()) as usize;
+    let u64_at = |at: usize| u64::from_le_bytes(elf[at..at + 8].try_into().unwrap()) as usize;
+    assert_eq!(&elf[..5], b"\x7fELF\x02");
+
+    const SHT_SYMTAB: usize = 2;
+    let (shoff, shentsize, shnum) = (u64_at(0x28), u16_at(0x3a), u16_at(0x3c));
+    let section = |idx: usize| shoff + idx * shentsize;
+    let symtab = (0..shnum).map(section).find(|&header| u32_at(header + 4) == SHT_SYMTAB).unwrap();
+    let strtab = section(u32_at(symtab + 0x28));
+    let (symbols, size, entsize) = (u64_at(symtab + 0x18), u64_at(symtab + 0x20), u64_at(symtab + 0x38));
+    let strings = u64_at(strtab + 0x18);
+
+    (symbols..symbols + size).step_by(entsize).map(|symbol| {
+        let name = &elf[strings + u32_at(symbol)..];
+        let end = name.iter().position(|&byte| byte == 0).unwrap();
+        ElfSymbol {
+            name: &name[..end],
+            kind: elf[symbol + 4] & 0xf,
+            value: u64_at(symbol + 8),
+            size: u64_at(symbol + 16),
+        }
+    }).collect()
+}
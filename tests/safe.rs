@@ -0,0 +1,29 @@
+#![deny(unsafe_code)]
+
+/// Read the time stamp counter.
+#[allow(unsafe_code)]
+#[direct_asm::assemble(backend = "gnu-as", safe)]
+extern "sysv64" fn rdtsc() -> u64 {
+    "rdtsc";
+    "shl rdx, 32";
+    "or rax, rdx";
+    "ret";
+}
+
+struct Cpu;
+
+impl Cpu {
+    #[allow(unsafe_code)]
+    #[direct_asm::assemble(backend = "gnu-as", safe)]
+    extern "sysv64" fn pause() {
+        "pause";
+        "ret";
+    }
+}
+
+#[test]
+fn safe_calls() {
+    let start = rdtsc();
+    Cpu::pause();
+    assert!(rdtsc() >= start);
+}
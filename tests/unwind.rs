@@ -0,0 +1,69 @@
+use std::backtrace::Backtrace;
+
+#[direct_asm::assemble]
+unsafe extern "C" fn derived(arg: *const u8, callback: unsafe extern "C" fn(*const u8)) {
+    "push %rbx";
+    "push %rbp";
+    "mov %rbp, %rsp";
+    "sub %rsp, 8";
+    "call {callback}";
+    "mov %rsp, %rbp";
+    "pop %rbp";
+    "pop %rbx";
+    "ret";
+}
+
+#[direct_asm::assemble]
+unsafe extern "C" fn explicit(arg: *const u8, callback: unsafe extern "C" fn(*const u8)) {
+    "push %rbx";
+    ".cfi_adjust_cfa_offset 8";
+    ".cfi_offset %rbx, -16";
+    "call {callback}";
+    "pop %rbx";
+    ".cfi_adjust_cfa_offset -8";
+    "ret";
+}
+
+unsafe extern "C" fn assert_unwinds(_: *const u8) {
+    let trace = Backtrace::force_capture().to_string();
+    assert!(trace.contains("through_assembled"), "Can not unwind to caller:\n{}", trace);
+}
+
+#[test]
+fn through_assembled() {
+    unsafe {
+        derived(std::ptr::null(), assert_unwinds);
+        explicit(std::ptr::null(), assert_unwinds);
+    }
+}
+
+#[direct_asm::assemble]
+unsafe extern "C" fn aligned(value: u64) -> u64 {
+    "push %rbp";
+    "mov %rbp, %rsp";
+    "and %rsp, -16";
+    "mov %rax, %rdi";
+    "mov %rsp, %rbp";
+    "pop %rbp";
+    "ret";
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+extern "C" {
+    /// The frame description entry covering `pc`, from the unwinder of libgcc.
+    fn _Unwind_Find_FDE(pc: *const u8, bases: *mut [usize; 3]) -> *const u8;
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn no_entry_for_unknown_stack_moves() {
+    let has_entry = |function: usize| {
+        let mut bases = [0; 3];
+        !unsafe { _Unwind_Find_FDE((function + 1) as *const u8, &mut bases) }.is_null()
+    };
+
+    assert_eq!(unsafe { aligned(42) }, 42);
+    assert!(has_entry(derived as *const () as usize));
+    // The frame can not be described after the stack pointer was aligned.
+    assert!(!has_entry(aligned as *const () as usize));
+}